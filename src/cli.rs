@@ -0,0 +1,109 @@
+use std::path::PathBuf;
+
+use chrono::NaiveDate;
+use clap::{Parser, Subcommand};
+
+use crate::client::observer::ProgressOutput;
+
+/// Transfer TV show episodes to a media centre over SSH.
+///
+/// Running without a command performs a sync, exactly as if `sync` had been given.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Read config from this file instead of the default locations
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Don't ask for confirmation before syncing
+    #[arg(long, short, global = true)]
+    pub yes: bool,
+
+    /// Show informational log messages as well as warnings
+    #[arg(long, short, global = true)]
+    pub verbose: bool,
+
+    /// How to show upload progress, instead of as set in config
+    #[arg(long, global = true, value_enum)]
+    pub progress: Option<ProgressOutput>,
+
+    /// Write JSON upload progress to this file, e.g. a named pipe, instead of as set in config
+    #[arg(long, global = true, value_name = "FILE")]
+    pub progress_file: Option<String>,
+
+    /// Read TV shows from this directory instead of the one in config
+    #[arg(long, global = true, value_name = "DIR")]
+    pub local_dir: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Upload any local episodes which are missing from the remote host
+    Sync {
+        /// Only print what would be uploaded, without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Print what a sync would do, without changing anything; the same as `sync --dry-run`
+    Plan,
+    /// List the TV shows on the remote host, or the episodes of one show
+    ListRemote {
+        /// The show to list episodes for
+        show: Option<String>,
+    },
+    /// Keep watching the local TV show folder, and sync new episodes once they finish downloading
+    Watch,
+    /// List previous syncs, or show the details of one
+    History {
+        /// The number of a sync to show the details of
+        run: Option<usize>,
+        /// Only list syncs which included this TV show or film
+        #[arg(long)]
+        show: Option<String>,
+        /// Only list syncs on or after this date (YYYY-MM-DD)
+        #[arg(long, value_name = "DATE")]
+        since: Option<NaiveDate>,
+        /// Only list syncs on or before this date (YYYY-MM-DD)
+        #[arg(long, value_name = "DATE")]
+        until: Option<NaiveDate>,
+    },
+    /// Undo a previous sync, moving the files it uploaded into the remote trash and restoring any
+    /// files it replaced
+    Undo {
+        /// The number of the sync to undo, as listed by `history`; defaults to the most recent
+        run: Option<usize>,
+        /// Delete the uploaded files instead of moving them into the trash
+        #[arg(long)]
+        delete: bool,
+    },
+    /// Check that the config file is valid and the remote host can be reached
+    CheckConfig,
+    /// Ask OSMC to refresh its library
+    Refresh,
+    /// Manage remembered mappings of local folder names to TV shows
+    Aliases {
+        #[command(subcommand)]
+        action: Option<AliasCommand>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum AliasCommand {
+    /// List all remembered aliases (the default)
+    List,
+    /// Always treat a local folder name as the given TV show
+    Add {
+        /// The local folder name
+        folder: String,
+        /// The TV show name, as it appears on the remote host
+        show: String,
+    },
+    /// Forget the alias for a local folder name
+    Remove {
+        /// The local folder name
+        folder: String,
+    },
+}
@@ -1,19 +1,67 @@
+#[cfg(test)]
+mod tests;
+
+mod checksum;
+pub mod observer;
 pub mod osmc;
+pub mod pool;
+pub mod throttle;
 pub mod upload;
 
 use std::fs::File;
-use std::io::{Error as IoError, Read};
+use std::io::{Error as IoError, Read, Seek, SeekFrom};
 use std::net::TcpStream;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use ::log::debug;
-use ssh2::{Error as SshError, Session};
+use ::log::{debug, info, warn};
+use chrono::{DateTime, Utc};
+use sha2::Sha256;
+use ssh2::{Error as SshError, OpenFlags, OpenType, Session, Sftp};
 use thiserror::Error;
 use typed_path::{Utf8UnixPath, Utf8UnixPathBuf};
 
+use crate::client::observer::{TransferEvent, TransferObserver};
+use crate::client::throttle::Throttle;
+
 pub struct SshClient {
     session: Session,
+    sftp: Sftp,
     tv_dir: Utf8UnixPathBuf,
+    movie_dir: Option<Utf8UnixPathBuf>,
+    opts: TransferOptions,
+}
+
+/// A file found on the remote, with its path relative to the directory it was listed from
+#[derive(Clone, Debug, PartialEq)]
+pub struct RemoteFile {
+    pub path: String,
+    pub size: u64,
+}
+
+/// A file to upload, and the existing remote file it replaces, if any
+#[derive(Clone, Debug)]
+pub struct Upload {
+    pub local: PathBuf,
+    pub remote: Utf8UnixPathBuf,
+    pub replaces: Option<Utf8UnixPathBuf>,
+}
+
+/// Settings controlling how files are transferred
+#[derive(Clone, Debug)]
+pub struct TransferOptions {
+    /// Check the remote copy against the local file before moving it into place
+    pub verify: bool,
+    /// How much of a file to read and write at a time
+    pub buffer_size: usize,
+}
+
+impl Default for TransferOptions {
+    fn default() -> Self {
+        TransferOptions {
+            verify: false,
+            buffer_size: upload::DEFAULT_BUFFER_SIZE,
+        }
+    }
 }
 
 #[derive(Error, Debug)]
@@ -28,6 +76,10 @@ pub enum ClientError {
     PlatformError,
     #[error("An unexpected threading error occurred")]
     Thread,
+    #[error(
+        "Uploaded copy of {0} doesn't match the local file, so it was discarded. Please try again."
+    )]
+    ChecksumMismatch(Utf8UnixPathBuf),
 }
 
 pub enum Auth {
@@ -39,6 +91,9 @@ type Result<T> = std::result::Result<T, ClientError>;
 
 const TEMP_PREFIX: &str = ".rusttv.tmp";
 
+/// Replaced files are moved into this folder within the TV or film directory, rather than deleted
+pub const TRASH_DIR: &str = ".rusttv-trash";
+
 /// Create a temporary path to upload a file to before moving it into the final path
 /// This makes rewriting partial, broken files less likely in event of a connection error
 fn temp_path(p: &Utf8UnixPath) -> Result<Utf8UnixPathBuf> {
@@ -49,39 +104,115 @@ fn temp_path(p: &Utf8UnixPath) -> Result<Utf8UnixPathBuf> {
     Ok(tmp)
 }
 
-impl SshClient {
-    /// Simple sanitisation to make sure the path works ok in a double-quoted shell string
-    /// This undoubtedly misses some edge cases but will work ok given injection isn't a problem
-    fn sanitise_shell_path(p: &Utf8UnixPath) -> Result<String> {
-        let s = p.as_str().to_string();
-        Ok(s.replace("\"", "\\\""))
+/// Find where a file under one of the given roots goes in the trash, keeping its path within the
+/// root, e.g. /tv/Show/S01 E01.mkv -> /tv/.rusttv-trash/Show/S01 E01.mkv
+fn trash_path(roots: &[Utf8UnixPathBuf], p: &Utf8UnixPath) -> Option<Utf8UnixPathBuf> {
+    roots.iter().find_map(|root| {
+        let relative = p.strip_prefix(root).ok()?;
+        Some(root.join(TRASH_DIR).join(relative))
+    })
+}
+
+// Trashed files are named for when they were trashed, e.g. S01 E01.mkv.20240314_200000123, so
+// that replacing the same file again keeps every earlier version
+fn trash_stamp(at: DateTime<Utc>) -> String {
+    at.format("%Y%m%d_%H%M%S%3f").to_string()
+}
+
+fn stamped_trash_path(trashed: &Utf8UnixPath, at: DateTime<Utc>) -> Option<Utf8UnixPathBuf> {
+    let filename = trashed.file_name()?;
+    Some(trashed.with_file_name(format!("{}.{}", filename, trash_stamp(at))))
+}
+
+/// Find which of the files in a trash folder is the version of `trashed` moved there by a sync
+/// which started at `since`: the first one trashed after that. Files trashed by older versions
+/// have no timestamp, and are used if nothing else is found.
+fn find_trashed(
+    trashed: &Utf8UnixPath,
+    since: DateTime<Utc>,
+    names: &[String],
+) -> Option<Utf8UnixPathBuf> {
+    let filename = trashed.file_name()?;
+    let since = trash_stamp(since);
+
+    let stamped = names
+        .iter()
+        .filter(|name| {
+            name.strip_prefix(filename)
+                .and_then(|rest| rest.strip_prefix('.'))
+                .is_some_and(|stamp| {
+                    stamp.len() == since.len()
+                        && stamp.chars().all(|c| c.is_ascii_digit() || c == '_')
+                        && stamp >= since.as_str()
+                })
+        })
+        .min();
+
+    match stamped {
+        Some(name) => Some(trashed.with_file_name(name)),
+        None if names.iter().any(|name| name == filename) => Some(trashed.to_path_buf()),
+        None => None,
     }
+}
+
+/// Quote a string for use as a single argument in a POSIX shell command
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// Convert a remote path into the form expected by the SFTP subsystem
+fn sftp_path(p: &Utf8UnixPath) -> &Path {
+    Path::new(p.as_str())
+}
 
+/// Pull the filename out of a path returned by SFTP, skipping anything which isn't valid UTF-8
+fn sftp_file_name(p: &Path) -> Option<String> {
+    p.file_name().and_then(|f| f.to_str()).map(String::from)
+}
+
+impl SshClient {
     pub fn connect(
         host: &str,
         port: usize,
         username: &str,
         auth: &Auth,
         tv_dir: &Utf8UnixPath,
+        opts: &TransferOptions,
     ) -> Result<SshClient> {
-        let mut client = SshClient {
-            session: Session::new()?,
-            tv_dir: tv_dir.to_path_buf(),
-        };
+        let mut session = Session::new()?;
         let conn = TcpStream::connect(format!("{host}:{port}"))?;
-        client.session.set_tcp_stream(conn);
-        client.session.handshake()?;
+        session.set_tcp_stream(conn);
+        session.handshake()?;
 
         match auth {
-            Auth::Password(pwd) => client.session.userauth_password(username, &pwd)?,
+            Auth::Password(pwd) => session.userauth_password(username, pwd)?,
             Auth::Privkey(file) => {
-                client
-                    .session
-                    .userauth_pubkey_file(username, None, Path::new(file), None)?
+                session.userauth_pubkey_file(username, None, Path::new(file), None)?
             }
         }
 
-        Ok(client)
+        let sftp = session.sftp()?;
+
+        Ok(SshClient {
+            session,
+            sftp,
+            tv_dir: tv_dir.to_path_buf(),
+            movie_dir: None,
+            opts: opts.clone(),
+        })
+    }
+
+    /// Also manage films, kept in the given directory
+    pub fn with_movie_dir(mut self, movie_dir: &Utf8UnixPath) -> SshClient {
+        self.movie_dir = Some(movie_dir.to_path_buf());
+        self
+    }
+
+    // All the directories we upload into
+    fn roots(&self) -> Vec<Utf8UnixPathBuf> {
+        let mut roots = vec![self.tv_dir.clone()];
+        roots.extend(self.movie_dir.clone());
+        roots
     }
 
     /// Execute an SSH command
@@ -98,67 +229,456 @@ impl SshClient {
         Ok(output)
     }
 
+    fn exists(&self, path: &Utf8UnixPath) -> bool {
+        self.sftp.stat(sftp_path(path)).is_ok()
+    }
+
+    /// Create the parent directory of the given path, along with any missing ancestors
     fn ensure_dir_exists(&mut self, path: &Utf8UnixPath) -> Result<()> {
         let dir = path.parent().ok_or(ClientError::EnsureDirError)?;
-        let dir_sane = Self::sanitise_shell_path(dir)?;
-        self.execute(&format!("mkdir -p \"{}\"", dir_sane))?;
+
+        for d in missing_dirs(dir, |d| self.exists(d)) {
+            debug!("sftp mkdir: {}", d);
+
+            // Another session uploading to the same new folder may have just created it
+            if let Err(e) = self.sftp.mkdir(sftp_path(&d), 0o755) {
+                if !self.exists(&d) {
+                    return Err(e.into());
+                }
+            }
+        }
         Ok(())
     }
 
+    /// List the directories which would need to be created to upload to the given path
+    pub fn dirs_to_create(&mut self, path: &Utf8UnixPath) -> Result<Vec<Utf8UnixPathBuf>> {
+        let dir = path.parent().ok_or(ClientError::EnsureDirError)?;
+        Ok(missing_dirs(dir, |d| self.exists(d)))
+    }
+
     fn mv(&mut self, src: &Utf8UnixPath, dest: &Utf8UnixPath) -> Result<()> {
-        self.execute(
-            &format!(
-                "mv \"{}\" \"{}\"",
-                Self::sanitise_shell_path(src)?,
-                Self::sanitise_shell_path(dest)?
-            )
-        )?;
+        debug!("sftp rename: {} -> {}", src, dest);
+        self.sftp.rename(sftp_path(src), sftp_path(dest), None)?;
+        Ok(())
+    }
+
+    /// Move a remote file into the trash, alongside anything previously trashed from the same
+    /// path, and return where it went
+    pub fn trash(&mut self, path: &Utf8UnixPath) -> Result<Utf8UnixPathBuf> {
+        let dest = trash_path(&self.roots(), path)
+            .and_then(|p| stamped_trash_path(&p, Utc::now()))
+            .ok_or(ClientError::PlatformError)?;
+        self.ensure_dir_exists(&dest)?;
+        self.mv(path, &dest)?;
+
+        Ok(dest)
+    }
+
+    // Find where a file replaced by a sync which started at `since` was moved to in the trash
+    fn find_in_trash(
+        &mut self,
+        old: &Utf8UnixPath,
+        since: DateTime<Utc>,
+    ) -> Result<Option<Utf8UnixPathBuf>> {
+        let trashed = trash_path(&self.roots(), old).ok_or(ClientError::PlatformError)?;
+        let dir = trashed.parent().ok_or(ClientError::PlatformError)?;
 
+        if !self.exists(dir) {
+            return Ok(None);
+        }
+        let names = self.list_dir(dir)?;
+
+        Ok(find_trashed(&trashed, since, &names))
+    }
+
+    /// Delete a remote file
+    pub fn remove(&mut self, path: &Utf8UnixPath) -> Result<()> {
+        debug!("sftp unlink: {}", path);
+        self.sftp.unlink(sftp_path(path))?;
         Ok(())
     }
 
-    /// Clear all temporary files left behind by interrupted runs
-    pub fn wipe_temp(&mut self) -> Result<()> {
-        self.execute(
-            &format!(
-                "find \"{}\" -type f -name \"{}.*\" -delete",
-                Self::sanitise_shell_path(&self.tv_dir)?,
-                TEMP_PREFIX
-            )
-        )?;
+    /// Undo an upload made by a sync which started at `since`: move the uploaded file into the
+    /// trash, or delete it, and put back the file it replaced, if any, from the trash. Returns
+    /// false if the uploaded file is already gone.
+    pub fn undo_upload(
+        &mut self,
+        remote: &Utf8UnixPath,
+        replaced: Option<&Utf8UnixPath>,
+        since: DateTime<Utc>,
+        delete: bool,
+    ) -> Result<bool> {
+        let restore = match replaced {
+            Some(old) => match self.find_in_trash(old, since)? {
+                Some(trashed) => Some((trashed, old)),
+                None => {
+                    warn!("Can't restore {}; it's no longer in the trash", old);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        let found = self.exists(remote);
+        let steps = undo_steps(remote, restore, delete, |p| self.exists(p));
+
+        for step in steps {
+            match step {
+                UndoStep::Move(src, dest) => self.mv(&src, &dest)?,
+                UndoStep::Trash(p) => {
+                    self.trash(&p)?;
+                }
+                UndoStep::Remove(p) => self.remove(&p)?,
+            }
+        }
+
+        Ok(found)
+    }
+
+    /// List the names of entries in a remote directory, ignoring hidden files
+    fn list_dir(&mut self, path: &Utf8UnixPath) -> Result<Vec<String>> {
+        let entries = self.sftp.readdir(sftp_path(path))?;
+
+        let mut names: Vec<String> = entries
+            .iter()
+            .filter_map(|(p, _)| sftp_file_name(p))
+            .filter(|name| !name.starts_with('.'))
+            .collect();
+        names.sort();
+
+        Ok(names)
+    }
+
+    /// Clear all temporary files left behind by interrupted runs, except those belonging to
+    /// any of the given destination paths, which may still be resumed
+    pub fn wipe_temp(&mut self, keep: &[Utf8UnixPathBuf]) -> Result<()> {
+        let keep_tmp = keep
+            .iter()
+            .map(|p| temp_path(p))
+            .collect::<Result<Vec<Utf8UnixPathBuf>>>()?;
+
+        let mut pending = self.roots();
+
+        while let Some(dir) = pending.pop() {
+            for (p, stat) in self.sftp.readdir(sftp_path(&dir))? {
+                let Some(name) = sftp_file_name(&p) else {
+                    continue;
+                };
+                let path = dir.join(&name);
+
+                if stat.is_dir() {
+                    pending.push(path);
+                } else if stat.is_file() && is_temp_file(&name) && !keep_tmp.contains(&path) {
+                    debug!("sftp unlink: {}", path);
+                    self.sftp.unlink(sftp_path(&path))?;
+                }
+            }
+        }
         Ok(())
     }
 
+    /// Calculate the SHA-256 of the first `len` bytes of a remote file, if the remote host
+    /// has the tools available to do so. This is the only operation which relies on a shell;
+    /// hosts without one will simply never resume uploads.
+    fn remote_sha256(&mut self, path: &Utf8UnixPath, len: u64) -> Option<String> {
+        let output = self
+            .execute(&format!(
+                "head -c {} {} | sha256sum",
+                len,
+                shell_quote(path.as_str())
+            ))
+            .ok()?;
+        checksum::parse_sha256sum(&output)
+    }
+
+    /// Calculate the SHA-256 of a whole remote file, preferring to let the remote host do the
+    /// work and falling back to reading the file back over SFTP if it can't
+    fn remote_sha256_full(&mut self, path: &Utf8UnixPath) -> Result<String> {
+        let output = self
+            .execute(&format!("sha256sum {}", shell_quote(path.as_str())))
+            .unwrap_or_default();
+
+        if let Some(digest) = checksum::parse_sha256sum(&output) {
+            return Ok(digest);
+        }
+
+        debug!(
+            "No remote sha256sum available; reading back {} to verify",
+            path
+        );
+        let mut f = self.sftp.open(sftp_path(path))?;
+        Ok(checksum::sha256_all(&mut f)?)
+    }
+
+    /// Work out how much of a temp file left behind by an interrupted upload can be kept.
+    /// The partial upload is only trusted if it's no bigger than the local file and its
+    /// checksum matches the same prefix of the local file; otherwise we start from scratch.
+    ///
+    /// Also returns a hasher primed with the kept prefix of the local file.
+    fn resume_offset(
+        &mut self,
+        local_file: &mut File,
+        tmp: &Utf8UnixPath,
+        size: u64,
+    ) -> Result<(u64, Sha256)> {
+        let fresh = (0, Sha256::default());
+
+        let existing = match self.sftp.stat(sftp_path(tmp)) {
+            Ok(stat) => stat.size.unwrap_or(0),
+            Err(_) => return Ok(fresh),
+        };
+
+        if existing == 0 || existing > size {
+            return Ok(fresh);
+        }
+
+        let local_hasher = checksum::sha256_prefix(local_file, existing)?;
+        let local_hash = checksum::hex_digest(local_hasher.clone());
+        local_file.seek(SeekFrom::Start(0))?;
+
+        match self.remote_sha256(tmp, existing) {
+            Some(remote_hash) if remote_hash == local_hash => Ok((existing, local_hasher)),
+            Some(_) => {
+                warn!(
+                    "Partial upload {} doesn't match local file; restarting",
+                    tmp
+                );
+                Ok(fresh)
+            }
+            None => {
+                warn!("Couldn't checksum partial upload {}; restarting", tmp);
+                Ok(fresh)
+            }
+        }
+    }
+
+    /// Make sure the uploaded temp file matches what we sent, discarding it if not
+    fn verify_upload(
+        &mut self,
+        tmp: &Utf8UnixPath,
+        remote: &Utf8UnixPath,
+        local_hash: &str,
+    ) -> Result<()> {
+        let remote_hash = self.remote_sha256_full(tmp)?;
+
+        if remote_hash != local_hash {
+            warn!(
+                "Checksum mismatch for {}: local {}, remote {}",
+                remote, local_hash, remote_hash
+            );
+            self.sftp.unlink(sftp_path(tmp))?;
+            return Err(ClientError::ChecksumMismatch(remote.to_path_buf()));
+        }
+
+        debug!("Verified {} (sha256 {})", remote, local_hash);
+        Ok(())
+    }
+
+    /// The size of a remote file
+    pub fn remote_size(&mut self, path: &Utf8UnixPath) -> Result<u64> {
+        let stat = self.sftp.stat(sftp_path(path))?;
+        Ok(stat.size.unwrap_or(0))
+    }
+
     pub fn list_shows(&mut self) -> Result<Vec<String>> {
-        let path_sane = Self::sanitise_shell_path(&self.tv_dir)?;
-        let output = self.execute(&format!("ls -1 \"{}\"", path_sane))?;
-        Ok(output.split_terminator("\n").map(String::from).collect())
+        let path = self.tv_dir.clone();
+        self.list_dir(&path)
+    }
+
+    pub fn list_movies(&mut self) -> Result<Vec<String>> {
+        match self.movie_dir.clone() {
+            Some(path) => self.list_dir(&path),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// List all files in a show's folder, including any in season folders, ignoring hidden files
+    pub fn list_episodes(&mut self, show: &str) -> Result<Vec<RemoteFile>> {
+        let root = self.tv_dir.join(show);
+
+        // A show we haven't uploaded before simply has no episodes yet
+        if !self.exists(&root) {
+            return Ok(vec![]);
+        }
+
+        let mut files = vec![];
+        let mut pending = vec![root.clone()];
+
+        while let Some(dir) = pending.pop() {
+            for (p, stat) in self.sftp.readdir(sftp_path(&dir))? {
+                let Some(name) = sftp_file_name(&p) else {
+                    continue;
+                };
+                if name.starts_with('.') {
+                    continue;
+                }
+                let path = dir.join(&name);
+
+                if stat.is_dir() {
+                    pending.push(path);
+                } else if let Ok(relative) = path.strip_prefix(&root) {
+                    files.push(RemoteFile {
+                        path: relative.to_string(),
+                        size: stat.size.unwrap_or(0),
+                    });
+                }
+            }
+        }
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Ok(files)
     }
 
-    pub fn list_episodes(&mut self, show: &str) -> Result<Vec<String>> {
-        let mut path = self.tv_dir.clone();
-        path.push(show);
-        let path_sane = Self::sanitise_shell_path(&path)?;
+    /// Upload a file over SFTP, picking up where a previous attempt left off if possible
+    ///
+    /// If the upload replaces an existing file, that file is only moved into the trash once the
+    /// new one has been fully uploaded (and verified, if enabled), just before it's moved into
+    /// place.
+    ///
+    /// The observer is told how the upload is going throughout, including if it fails.
+    pub fn upload_file(
+        &mut self,
+        upload: &Upload,
+        throttle: &Throttle,
+        observer: &dyn TransferObserver,
+    ) -> Result<()> {
+        let remote = upload.remote.as_path();
+        let label = self
+            .roots()
+            .iter()
+            .find_map(|root| remote.strip_prefix(root).ok())
+            .unwrap_or(remote)
+            .to_string();
 
-        let output = self.execute(&format!("ls -1 \"{}\"", path_sane))?;
-        Ok(output.split_terminator("\n").map(String::from).collect())
+        let res = self.try_upload_file(upload, &label, throttle, observer);
+        if let Err(e) = &res {
+            observer.notify(&TransferEvent::Failed {
+                file: &label,
+                error: e.to_string(),
+            });
+        }
+        res
     }
 
-    /// Upload a file over SSH
-    pub fn upload_file(&mut self, local: &Path, remote: &Utf8UnixPath) -> Result<()> {
+    fn try_upload_file(
+        &mut self,
+        upload: &Upload,
+        label: &str,
+        throttle: &Throttle,
+        observer: &dyn TransferObserver,
+    ) -> Result<()> {
+        let (local, remote) = (upload.local.as_path(), upload.remote.as_path());
         debug!("Uploading file: {:?} -> {:?}", local, remote);
         self.ensure_dir_exists(remote)?;
         let tmp = temp_path(remote)?;
 
         let size = local.metadata()?.len();
-        let out_chan = self.session.scp_send(&Path::new(&tmp.as_str()), 0o644, size, None)?;
+        let mut local_file = File::open(local)?;
+        let (offset, hasher) = self.resume_offset(&mut local_file, &tmp, size)?;
+        let hasher = if self.opts.verify { Some(hasher) } else { None };
+
+        observer.notify(&TransferEvent::Started {
+            file: label,
+            size,
+            offset,
+        });
 
-        let local_file = File::open(local)?;
+        let out_file = if offset == 0 {
+            self.sftp.create(sftp_path(&tmp))?
+        } else {
+            info!(
+                "Resuming upload of {} from byte {} of {}",
+                remote, offset, size
+            );
+
+            let mut f =
+                self.sftp
+                    .open_mode(sftp_path(&tmp), OpenFlags::WRITE, 0o644, OpenType::File)?;
+            f.seek(SeekFrom::Start(offset))?;
+            f
+        };
+        local_file.seek(SeekFrom::Start(offset))?;
+
+        let (mut out_file, hasher) = upload::handle_upload(
+            local_file,
+            out_file,
+            hasher,
+            offset,
+            self.opts.buffer_size,
+            throttle,
+            observer,
+            label,
+        )?;
+        out_file.close()?;
+
+        if let Some(h) = hasher {
+            observer.notify(&TransferEvent::Verifying { file: label });
+            self.verify_upload(&tmp, remote, &checksum::hex_digest(h))?;
+        }
+
+        if let Some(old) = &upload.replaces {
+            let trashed = self.trash(old)?;
+            info!("Moved replaced file {} to {}", old, trashed);
+        }
 
-        upload::handle_upload(local_file, out_chan, size)?;
         self.mv(&tmp, remote)?;
         debug!("Completed upload");
+        observer.notify(&TransferEvent::Completed { file: label });
 
         Ok(())
     }
 }
+
+fn is_temp_file(name: &str) -> bool {
+    name.starts_with(&format!("{}.", TEMP_PREFIX))
+}
+
+/// A single remote file operation in undoing an upload
+#[derive(Debug, PartialEq)]
+enum UndoStep {
+    Move(Utf8UnixPathBuf, Utf8UnixPathBuf),
+    Trash(Utf8UnixPathBuf),
+    Remove(Utf8UnixPathBuf),
+}
+
+/// Work out, in order, the steps to undo an upload: trash or delete the uploaded file, if it's
+/// still there, then move the file it replaced, if any, back from where it is in the trash
+fn undo_steps<F: FnMut(&Utf8UnixPath) -> bool>(
+    remote: &Utf8UnixPath,
+    restore: Option<(Utf8UnixPathBuf, &Utf8UnixPath)>,
+    delete: bool,
+    mut exists: F,
+) -> Vec<UndoStep> {
+    let mut steps = vec![];
+
+    // The replaced file may have had the same path as the upload, so that has to go first
+    if exists(remote) && delete {
+        steps.push(UndoStep::Remove(remote.to_path_buf()));
+    } else if exists(remote) {
+        steps.push(UndoStep::Trash(remote.to_path_buf()));
+    }
+    if let Some((trashed, old)) = restore {
+        steps.push(UndoStep::Move(trashed, old.to_path_buf()));
+    }
+
+    steps
+}
+
+/// Find which directories along the given path don't exist yet, outermost first, so that they
+/// can be created in order
+fn missing_dirs<F: FnMut(&Utf8UnixPath) -> bool>(
+    dir: &Utf8UnixPath,
+    mut exists: F,
+) -> Vec<Utf8UnixPathBuf> {
+    let mut missing = vec![];
+
+    for d in dir.ancestors() {
+        if d.as_str().is_empty() || exists(d) {
+            break;
+        }
+        missing.push(d.to_path_buf());
+    }
+
+    missing.reverse();
+    missing
+}
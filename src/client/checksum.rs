@@ -0,0 +1,39 @@
+#[cfg(test)]
+mod tests;
+
+use std::io::{self, Read};
+
+use sha2::{Digest, Sha256};
+
+/// Hash the first `len` bytes of the reader with SHA-256, returning the hasher so that the rest
+/// of the data can be fed into it later
+pub(super) fn sha256_prefix<R: Read>(reader: &mut R, len: u64) -> io::Result<Sha256> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut reader.take(len), &mut hasher)?;
+
+    Ok(hasher)
+}
+
+/// Hash everything remaining in the reader with SHA-256, returning a hex digest
+pub(super) fn sha256_all<R: Read>(reader: &mut R) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(reader, &mut hasher)?;
+
+    Ok(hex_digest(hasher))
+}
+
+/// Finish hashing, returning a lowercase hex digest in the same format produced by `sha256sum`
+pub(super) fn hex_digest(hasher: Sha256) -> String {
+    format!("{:x}", hasher.finalize())
+}
+
+/// Extract the digest from the output of `sha256sum`, if it looks valid
+pub(super) fn parse_sha256sum(output: &str) -> Option<String> {
+    let digest = output.split_whitespace().next()?;
+
+    if digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit()) {
+        Some(digest.to_lowercase())
+    } else {
+        None
+    }
+}
@@ -0,0 +1,45 @@
+use super::*;
+
+// sha256 of "hello"
+const HELLO_SHA256: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+#[test]
+fn sha256_prefix_only_hashes_prefix() {
+    let mut data: &[u8] = b"hello world";
+    let actual = hex_digest(sha256_prefix(&mut data, 5).unwrap());
+    assert_eq!(actual, HELLO_SHA256);
+}
+
+#[test]
+fn sha256_prefix_whole_input() {
+    let mut data: &[u8] = b"hello";
+    let actual = hex_digest(sha256_prefix(&mut data, 100).unwrap());
+    assert_eq!(actual, HELLO_SHA256);
+}
+
+#[test]
+fn sha256_prefix_can_be_continued() {
+    let mut data: &[u8] = b"hello";
+    let mut hasher = sha256_prefix(&mut data, 2).unwrap();
+    hasher.update(b"llo");
+    assert_eq!(hex_digest(hasher), HELLO_SHA256);
+}
+
+#[test]
+fn sha256_all_matches_prefix() {
+    let mut data: &[u8] = b"hello";
+    assert_eq!(sha256_all(&mut data).unwrap(), HELLO_SHA256);
+}
+
+#[test]
+fn parse_sha256sum_valid() {
+    let output = format!("{}  -\n", HELLO_SHA256);
+    assert_eq!(parse_sha256sum(&output), Some(HELLO_SHA256.to_string()));
+}
+
+#[test]
+fn parse_sha256sum_invalid() {
+    for output in ["", "sh: sha256sum: not found", "abc123  -"] {
+        assert_eq!(parse_sha256sum(output), None);
+    }
+}
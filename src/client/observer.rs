@@ -0,0 +1,206 @@
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::{Error as IoError, ErrorKind, Result as IoResult, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use clap::ValueEnum;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
+
+/// Something which happened while uploading. Files are identified by their path relative to the
+/// remote directory they're uploaded to.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum TransferEvent<'a> {
+    BatchStarted {
+        files: usize,
+        bytes: u64,
+    },
+    // An upload is starting, or resuming from `offset` bytes in
+    Started {
+        file: &'a str,
+        size: u64,
+        offset: u64,
+    },
+    // Another `bytes` have been sent, making `sent` in total
+    Progress {
+        file: &'a str,
+        bytes: u64,
+        sent: u64,
+    },
+    Verifying {
+        file: &'a str,
+    },
+    Completed {
+        file: &'a str,
+    },
+    Failed {
+        file: &'a str,
+        error: String,
+    },
+    BatchFinished,
+}
+
+/// Receives events from uploads as they happen, to report on them. Concurrent uploads share the
+/// same observer, so it'll be notified from several threads at once.
+pub trait TransferObserver: Sync {
+    fn notify(&self, event: &TransferEvent);
+}
+
+/// How to report on uploads
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum ProgressOutput {
+    // A progress bar per file, and one for the whole batch
+    Bars,
+    // One JSON object per line for each event, for scripts, written to a file of their own so
+    // they aren't mixed up with everything else printed
+    Json,
+    None,
+}
+
+impl ProgressOutput {
+    /// Create an observer to report progress this way. JSON events are appended to `file`, which
+    /// may be a named pipe or e.g. /dev/fd/3, and is required for them.
+    pub fn observer(&self, file: Option<&Path>) -> IoResult<Box<dyn TransferObserver>> {
+        let observer: Box<dyn TransferObserver> = match self {
+            ProgressOutput::Bars => Box::new(ProgressBars::new()),
+            ProgressOutput::Json => {
+                let path = file.ok_or_else(|| {
+                    IoError::new(ErrorKind::InvalidInput, "no progress_file to write JSON to")
+                })?;
+                let out = OpenOptions::new().create(true).append(true).open(path)?;
+                Box::new(JsonLines::new(out))
+            }
+            ProgressOutput::None => Box::new(NoOp),
+        };
+
+        Ok(observer)
+    }
+}
+
+/// Ignores everything
+pub struct NoOp;
+
+impl TransferObserver for NoOp {
+    fn notify(&self, _event: &TransferEvent) {}
+}
+
+/// Writes each event as a line of JSON
+pub struct JsonLines<W: Write + Send> {
+    out: Mutex<W>,
+}
+
+impl<W: Write + Send> JsonLines<W> {
+    pub fn new(out: W) -> JsonLines<W> {
+        JsonLines {
+            out: Mutex::new(out),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<W: Write + Send> TransferObserver for JsonLines<W> {
+    fn notify(&self, event: &TransferEvent) {
+        let mut out = self.out.lock().unwrap_or_else(|e| e.into_inner());
+
+        // Reporting progress mustn't get in the way of the upload itself
+        if serde_json::to_writer(&mut *out, event).is_ok() {
+            let _ = writeln!(out);
+            let _ = out.flush();
+        }
+    }
+}
+
+/// Progress bars for the terminal: one per file being uploaded, plus an overall bar
+pub struct ProgressBars {
+    multi: MultiProgress,
+    overall: ProgressBar,
+    files: Mutex<HashMap<String, ProgressBar>>,
+}
+
+impl ProgressBars {
+    pub fn new() -> ProgressBars {
+        let multi = MultiProgress::new();
+        let overall = multi.add(
+            ProgressBar::new(0).with_style(
+                ProgressStyle::with_template(
+                    "{prefix:>40!} {wide_bar:.cyan/blue} {bytes}/{total_bytes} {eta} left {msg:.green} ",
+                )
+                .unwrap(),
+            ),
+        );
+        overall.set_prefix("Total");
+
+        ProgressBars {
+            multi,
+            overall,
+            files: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn file_bar(&self, label: &str, size: u64) -> ProgressBar {
+        let bar = self.multi.insert_before(
+            &self.overall,
+            ProgressBar::new(size).with_style(
+                ProgressStyle::with_template(
+                    "{prefix:>40!} {wide_bar:.green/blue} {eta} left ({bytes_per_sec}) {percent}% {msg:.green} ",
+                )
+                .unwrap(),
+            ),
+        );
+        bar.set_prefix(label.to_string());
+        bar
+    }
+}
+
+impl Default for ProgressBars {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TransferObserver for ProgressBars {
+    fn notify(&self, event: &TransferEvent) {
+        let mut files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+
+        match event {
+            TransferEvent::BatchStarted { bytes, .. } => self.overall.set_length(*bytes),
+            TransferEvent::Started { file, size, offset } => {
+                let bar = self.file_bar(file, *size);
+                bar.set_position(*offset);
+                self.overall.inc(*offset);
+                files.insert(file.to_string(), bar);
+            }
+            TransferEvent::Progress { file, bytes, .. } => {
+                if let Some(bar) = files.get(*file) {
+                    bar.inc(*bytes);
+                }
+                self.overall.inc(*bytes);
+            }
+            TransferEvent::Verifying { file } => {
+                if let Some(bar) = files.get(*file) {
+                    bar.set_message("verifying");
+                }
+            }
+            TransferEvent::Completed { file } => {
+                if let Some(bar) = files.remove(*file) {
+                    bar.finish_with_message("OK");
+                }
+            }
+            TransferEvent::Failed { file, .. } => {
+                if let Some(bar) = files.remove(*file) {
+                    bar.abandon_with_message("FAILED");
+                }
+            }
+            TransferEvent::BatchFinished => self.overall.finish_with_message("OK"),
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use super::*;
+
+#[test]
+fn test_json_lines() {
+    let json = JsonLines::new(vec![]);
+    json.notify(&TransferEvent::BatchStarted {
+        files: 1,
+        bytes: 100,
+    });
+    json.notify(&TransferEvent::Started {
+        file: "All My Circuits/S01 E01.mkv",
+        size: 100,
+        offset: 20,
+    });
+    json.notify(&TransferEvent::Progress {
+        file: "All My Circuits/S01 E01.mkv",
+        bytes: 80,
+        sent: 100,
+    });
+    json.notify(&TransferEvent::Failed {
+        file: "All My Circuits/S01 E01.mkv",
+        error: "it broke".to_string(),
+    });
+    json.notify(&TransferEvent::BatchFinished);
+
+    let actual = String::from_utf8(json.into_inner()).unwrap();
+    let expected = [
+        r#"{"event":"batch_started","files":1,"bytes":100}"#,
+        r#"{"event":"started","file":"All My Circuits/S01 E01.mkv","size":100,"offset":20}"#,
+        r#"{"event":"progress","file":"All My Circuits/S01 E01.mkv","bytes":80,"sent":100}"#,
+        r#"{"event":"failed","file":"All My Circuits/S01 E01.mkv","error":"it broke"}"#,
+        r#"{"event":"batch_finished"}"#,
+    ];
+
+    assert_eq!(actual.lines().collect::<Vec<&str>>(), expected);
+}
+
+#[test]
+fn test_progress_output() {
+    assert_eq!(
+        ProgressOutput::from_str("json", true),
+        Ok(ProgressOutput::Json)
+    );
+    assert!(ProgressOutput::from_str("sparkles", true).is_err());
+}
@@ -2,6 +2,7 @@ use thiserror::Error;
 use ureq;
 
 const SIG_SCAN: &str = "VideoLibrary.Scan";
+const SIG_CLEAN: &str = "VideoLibrary.Clean";
 
 #[derive(Error, Debug)]
 pub enum OsmcError {
@@ -75,4 +76,9 @@ impl OsmcClient {
     pub fn trigger_refresh(&self) -> Result<()> {
         self.send_signal(SIG_SCAN)
     }
+
+    /// Ask OSMC to remove library entries whose files no longer exist
+    pub fn trigger_clean(&self) -> Result<()> {
+        self.send_signal(SIG_CLEAN)
+    }
 }
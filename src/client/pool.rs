@@ -0,0 +1,73 @@
+use super::observer::{TransferEvent, TransferObserver};
+use super::throttle::Throttle;
+use super::{ClientError, Result, SshClient, Upload};
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use ::log::error;
+
+/// Upload a batch of files, spreading them across the given clients so that each client (and
+/// therefore each SSH session) uploads one file at a time, concurrently with the others.
+///
+/// If any upload fails, the remaining clients finish their current file and then stop. The
+/// result of each upload is returned in the same order as the files, with `None` for any which
+/// weren't attempted. The throttle limits the combined rate of all the uploads, and the observer
+/// is told how each of them is going.
+pub fn upload_all(
+    clients: Vec<SshClient>,
+    files: &[Upload],
+    throttle: &Throttle,
+    observer: &dyn TransferObserver,
+) -> Result<Vec<Option<Result<()>>>> {
+    let mut total: u64 = 0;
+    for upload in files {
+        total += upload.local.metadata()?.len();
+    }
+
+    observer.notify(&TransferEvent::BatchStarted {
+        files: files.len(),
+        bytes: total,
+    });
+    let queue: Mutex<VecDeque<(usize, &Upload)>> = Mutex::new(files.iter().enumerate().collect());
+    let by_file: Mutex<Vec<Option<Result<()>>>> = Mutex::new(files.iter().map(|_| None).collect());
+    let failed = AtomicBool::new(false);
+
+    let (queue, outcomes, failed) = (&queue, &by_file, &failed);
+
+    let results: Vec<Result<()>> = thread::scope(|s| {
+        let workers: Vec<_> = clients
+            .into_iter()
+            .map(|mut client| {
+                s.spawn(move || -> Result<()> {
+                    while !failed.load(Ordering::SeqCst) {
+                        let next = queue.lock().map_err(|_| ClientError::Thread)?.pop_front();
+                        let Some((i, upload)) = next else {
+                            break;
+                        };
+
+                        let res = client.upload_file(upload, throttle, observer);
+                        if let Err(e) = &res {
+                            error!("Upload of {} failed: {}", upload.remote, e);
+                            failed.store(true, Ordering::SeqCst);
+                        }
+                        outcomes.lock().map_err(|_| ClientError::Thread)?[i] = Some(res);
+                    }
+                    Ok(())
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .map(|w| w.join().unwrap_or(Err(ClientError::Thread)))
+            .collect()
+    });
+
+    results.into_iter().collect::<Result<Vec<()>>>()?;
+    observer.notify(&TransferEvent::BatchFinished);
+
+    by_file.into_inner().map_err(|_| ClientError::Thread)
+}
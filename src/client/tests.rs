@@ -0,0 +1,187 @@
+use super::*;
+
+use std::collections::HashMap;
+
+#[test]
+fn temp_path_prefixes_filename() {
+    let expected = Utf8UnixPathBuf::from("/tv/All My Circuits/.rusttv.tmp.S01 E02.mkv");
+    let actual = temp_path(Utf8UnixPath::new("/tv/All My Circuits/S01 E02.mkv")).unwrap();
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn temp_files_recognised() {
+    assert!(is_temp_file(".rusttv.tmp.S01 E02.mkv"));
+    assert!(!is_temp_file("S01 E02.mkv"));
+    assert!(!is_temp_file(".rusttv.tmpfoo"));
+}
+
+#[test]
+fn shell_quote_special_chars() {
+    assert_eq!(shell_quote("$HOME `ls`"), "'$HOME `ls`'");
+    assert_eq!(shell_quote("Bob's Burgers"), "'Bob'\\''s Burgers'");
+}
+
+#[test]
+fn missing_dirs_outermost_first() {
+    let existing = ["/", "/tv"];
+
+    let expected = vec![
+        Utf8UnixPathBuf::from("/tv/Hypnotoad"),
+        Utf8UnixPathBuf::from("/tv/Hypnotoad/Season 01"),
+    ];
+    let actual = missing_dirs(Utf8UnixPath::new("/tv/Hypnotoad/Season 01"), |d| {
+        existing.contains(&d.as_str())
+    });
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn missing_dirs_none_missing() {
+    let actual = missing_dirs(Utf8UnixPath::new("/tv/Hypnotoad"), |_| true);
+    assert!(actual.is_empty());
+}
+
+#[test]
+fn trash_path_keeps_relative_path() {
+    let roots = [
+        Utf8UnixPathBuf::from("/tv"),
+        Utf8UnixPathBuf::from("/films"),
+    ];
+
+    assert_eq!(
+        trash_path(&roots, Utf8UnixPath::new("/tv/Hypnotoad/S01 E01.mkv")),
+        Some(Utf8UnixPathBuf::from(
+            "/tv/.rusttv-trash/Hypnotoad/S01 E01.mkv"
+        ))
+    );
+    assert_eq!(
+        trash_path(
+            &roots,
+            Utf8UnixPath::new("/films/Hypnotoad (3000)/Hypnotoad (3000).mkv")
+        ),
+        Some(Utf8UnixPathBuf::from(
+            "/films/.rusttv-trash/Hypnotoad (3000)/Hypnotoad (3000).mkv"
+        ))
+    );
+    assert_eq!(
+        trash_path(&roots, Utf8UnixPath::new("/elsewhere/file.mkv")),
+        None
+    );
+}
+
+fn at(time: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(time)
+        .unwrap()
+        .with_timezone(&Utc)
+}
+
+#[test]
+fn trashed_files_are_timestamped() {
+    let trashed = Utf8UnixPath::new("/tv/.rusttv-trash/Hypnotoad/S01 E01.mkv");
+    assert_eq!(
+        stamped_trash_path(trashed, at("2024-03-14T20:00:00.123Z")),
+        Some(Utf8UnixPathBuf::from(
+            "/tv/.rusttv-trash/Hypnotoad/S01 E01.mkv.20240314_200000123"
+        ))
+    );
+}
+
+#[test]
+fn find_trashed_by_sync_time() {
+    let trashed = Utf8UnixPath::new("/tv/.rusttv-trash/Hypnotoad/S01 E01.mkv");
+    let names: Vec<String> = [
+        "S01 E01.mkv",
+        "S01 E01.mkv.20240301_100000000",
+        "S01 E01.mkv.20240314_200005000",
+        "S01 E01.mkv.20240401_090000000",
+        "S01 E01.mp4.20240314_200005000",
+    ]
+    .iter()
+    .map(|n| n.to_string())
+    .collect();
+    let found = |since| find_trashed(trashed, at(since), &names).map(|p| p.to_string());
+
+    // The version replaced by each sync, not an earlier or later one
+    assert_eq!(
+        found("2024-03-14T20:00:00Z").as_deref(),
+        Some("/tv/.rusttv-trash/Hypnotoad/S01 E01.mkv.20240314_200005000")
+    );
+    assert_eq!(
+        found("2024-03-01T09:59:00Z").as_deref(),
+        Some("/tv/.rusttv-trash/Hypnotoad/S01 E01.mkv.20240301_100000000")
+    );
+    // Nothing since, so it's something trashed before timestamps were added
+    assert_eq!(
+        found("2024-05-01T00:00:00Z").as_deref(),
+        Some("/tv/.rusttv-trash/Hypnotoad/S01 E01.mkv")
+    );
+    assert_eq!(
+        find_trashed(trashed, at("2024-05-01T00:00:00Z"), &names[1..]),
+        None
+    );
+}
+
+fn in_trash(name: &str) -> Utf8UnixPathBuf {
+    Utf8UnixPathBuf::from("/tv/.rusttv-trash/Hypnotoad").join(name)
+}
+
+// Apply undo steps to a fake remote, mapping paths to file contents
+fn apply_undo(files: &mut HashMap<Utf8UnixPathBuf, &'static str>, steps: Vec<UndoStep>) {
+    let roots = [Utf8UnixPathBuf::from("/tv")];
+
+    for step in steps {
+        match step {
+            UndoStep::Move(src, dest) => {
+                let contents = files.remove(&src).unwrap();
+                files.insert(dest, contents);
+            }
+            UndoStep::Trash(p) => {
+                let contents = files.remove(&p).unwrap();
+                let trashed = trash_path(&roots, &p).unwrap();
+                let dest = stamped_trash_path(&trashed, at("2024-04-01T09:00:00Z")).unwrap();
+                files.insert(dest, contents);
+            }
+            UndoStep::Remove(p) => {
+                files.remove(&p).unwrap();
+            }
+        }
+    }
+}
+
+#[test]
+fn undo_restores_replaced_file_with_same_path() {
+    let remote = Utf8UnixPathBuf::from("/tv/Hypnotoad/S01 E01.mkv");
+    let trashed = in_trash("S01 E01.mkv.20240314_200005000");
+    let mut files = HashMap::from([(remote.clone(), "upgrade"), (trashed.clone(), "original")]);
+
+    let restore = Some((trashed, remote.as_path()));
+    let steps = undo_steps(&remote, restore, false, |p| files.contains_key(p));
+    apply_undo(&mut files, steps);
+
+    let undone = in_trash("S01 E01.mkv.20240401_090000000");
+    let expected = HashMap::from([(remote.clone(), "original"), (undone, "upgrade")]);
+    assert_eq!(files, expected);
+}
+
+#[test]
+fn undo_restores_replaced_file_with_other_path() {
+    let remote = Utf8UnixPathBuf::from("/tv/Hypnotoad/S01 E01.mkv");
+    let old = Utf8UnixPathBuf::from("/tv/Hypnotoad/S01 E01.mp4");
+    let trashed = in_trash("S01 E01.mp4");
+    let mut files = HashMap::from([(remote.clone(), "upgrade"), (trashed.clone(), "original")]);
+
+    let restore = Some((trashed, old.as_path()));
+    let steps = undo_steps(&remote, restore, true, |p| files.contains_key(p));
+    apply_undo(&mut files, steps);
+
+    assert_eq!(files, HashMap::from([(old, "original")]));
+}
+
+#[test]
+fn undo_skips_missing_files() {
+    let remote = Utf8UnixPathBuf::from("/tv/Hypnotoad/S01 E01.mkv");
+
+    let steps = undo_steps(&remote, None, false, |_| false);
+    assert!(steps.is_empty());
+}
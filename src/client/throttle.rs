@@ -0,0 +1,192 @@
+#[cfg(test)]
+mod tests;
+
+use std::fmt;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::{Local, NaiveTime};
+use regex::Regex;
+use serde::Deserialize;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ThrottleError {
+    #[error("bad transfer rate \"{0}\"; use a rate like \"5MB/s\", or \"40Mb/s\" in bits")]
+    BadRate(String),
+    #[error("bad time range \"{0}\"; use two different 24-hour times, like \"08:00-23:30\"")]
+    BadHours(String),
+}
+
+// Compiled once, as rates are parsed whenever config is read
+static RATE_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"^\s*(?<num>[0-9]+(?:\.[0-9]+)?)\s*",
+        r"(?<unit>(?i:[kmg]i?))?(?<b>[bB])?(?:/s)?\s*$"
+    ))
+    .unwrap()
+});
+
+/// A transfer rate in bytes per second, written like `5MB/s`, `750KiB/s` or `1.5MB`. Units with
+/// an `i` are powers of 1024, and the rest powers of 1000. A lowercase `b` means bits, as in
+/// `40Mb/s`, and an uppercase one bytes.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(try_from = "String")]
+pub struct Rate(u64);
+
+impl Rate {
+    pub fn new(rate: &str) -> Result<Rate, ThrottleError> {
+        let bad = || ThrottleError::BadRate(rate.to_string());
+
+        let caps = RATE_PATTERN.captures(rate).ok_or_else(bad)?;
+        let num: f64 = caps["num"].parse().map_err(|_| bad())?;
+        let unit = caps.name("unit").map(|u| u.as_str().to_lowercase());
+
+        let multiplier: f64 = match unit.as_deref() {
+            None => 1.0,
+            Some("k") => 1e3,
+            Some("m") => 1e6,
+            Some("g") => 1e9,
+            Some("ki") => 1024.0,
+            Some("mi") => 1024.0 * 1024.0,
+            Some("gi") => 1024.0 * 1024.0 * 1024.0,
+            Some(_) => return Err(bad()),
+        };
+
+        let bits = caps.name("b").is_some_and(|b| b.as_str() == "b");
+        let bytes = if bits {
+            num * multiplier / 8.0
+        } else {
+            num * multiplier
+        };
+
+        match bytes as u64 {
+            0 => Err(bad()),
+            bytes => Ok(Rate(bytes)),
+        }
+    }
+
+    pub fn bytes_per_sec(&self) -> u64 {
+        self.0
+    }
+}
+
+impl TryFrom<String> for Rate {
+    type Error = ThrottleError;
+
+    fn try_from(rate: String) -> Result<Self, Self::Error> {
+        Self::new(&rate)
+    }
+}
+
+/// A daily range of times like `08:00-23:30`, which may wrap past midnight, e.g. `22:00-06:00`
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(try_from = "String")]
+pub struct Hours {
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+impl Hours {
+    pub fn new(hours: &str) -> Result<Hours, ThrottleError> {
+        let bad = || ThrottleError::BadHours(hours.to_string());
+        let parse = |t: &str| NaiveTime::parse_from_str(t.trim(), "%H:%M").map_err(|_| bad());
+
+        let (start, end) = hours.split_once('-').ok_or_else(bad)?;
+        let (start, end) = (parse(start)?, parse(end)?);
+
+        // Otherwise it's ambiguous whether this is all day or never
+        if start == end {
+            return Err(bad());
+        }
+
+        Ok(Hours { start, end })
+    }
+
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+impl TryFrom<String> for Hours {
+    type Error = ThrottleError;
+
+    fn try_from(hours: String) -> Result<Self, Self::Error> {
+        Self::new(&hours)
+    }
+}
+
+impl fmt::Display for Hours {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        )
+    }
+}
+
+/// Limits the combined rate of every upload sharing it. Clones share the same limit, so one
+/// throttle can be handed to each concurrent upload.
+#[derive(Clone, Default)]
+pub struct Throttle {
+    limit: Option<Arc<Limit>>,
+}
+
+struct Limit {
+    rate: Rate,
+    // Only limit during these hours, if given
+    hours: Option<Hours>,
+    // When everything allowed through so far will have been sent, at the limited rate
+    free_at: Mutex<Instant>,
+}
+
+impl Throttle {
+    pub fn new(rate: Rate, hours: Option<Hours>) -> Throttle {
+        Throttle {
+            limit: Some(Arc::new(Limit {
+                rate,
+                hours,
+                free_at: Mutex::new(Instant::now()),
+            })),
+        }
+    }
+
+    /// Block until another `n` bytes can be sent without going over the limit
+    pub fn wait(&self, n: usize) {
+        let Some(limit) = &self.limit else {
+            return;
+        };
+        if limit
+            .hours
+            .is_some_and(|h| !h.contains(Local::now().time()))
+        {
+            return;
+        }
+
+        let delay = limit.reserve(n, Instant::now());
+        if !delay.is_zero() {
+            thread::sleep(delay);
+        }
+    }
+}
+
+impl Limit {
+    // Book the time it'll take to send `n` bytes after everything already booked, returning how
+    // long to wait before sending them. Time spent idle isn't saved up to allow a burst later.
+    fn reserve(&self, n: usize, now: Instant) -> Duration {
+        let cost = Duration::from_secs_f64(n as f64 / self.rate.bytes_per_sec() as f64);
+
+        let mut free_at = self.free_at.lock().unwrap_or_else(|e| e.into_inner());
+        let start = (*free_at).max(now);
+        *free_at = start + cost;
+
+        start - now
+    }
+}
@@ -0,0 +1,80 @@
+use super::*;
+
+#[test]
+fn test_parse_rate() {
+    let cases = vec![
+        ("5MB/s", 5_000_000),
+        ("5 mB/s", 5_000_000),
+        ("40Mb/s", 5_000_000),
+        ("8 kb", 1000),
+        ("750KiB/s", 750 * 1024),
+        ("1.5MB", 1_500_000),
+        ("2GiB/s", 2 * 1024 * 1024 * 1024),
+        ("1000", 1000),
+        ("1000B/s", 1000),
+    ];
+
+    for (raw, expected) in cases {
+        assert_eq!(
+            Rate::new(raw).map(|r| r.bytes_per_sec()),
+            Ok(expected),
+            "{}",
+            raw
+        );
+    }
+}
+
+#[test]
+fn test_parse_bad_rate() {
+    for raw in ["", "fast", "5TB/s", "0MB/s", "-5MB/s", "5MB/h", "1b/s"] {
+        assert_eq!(Rate::new(raw), Err(ThrottleError::BadRate(raw.to_string())));
+    }
+}
+
+#[test]
+fn test_hours() {
+    let time = |t: &str| NaiveTime::parse_from_str(t, "%H:%M").unwrap();
+
+    let day = Hours::new("08:00-23:30").unwrap();
+    assert!(day.contains(time("08:00")));
+    assert!(day.contains(time("12:00")));
+    assert!(!day.contains(time("23:30")));
+    assert!(!day.contains(time("03:00")));
+
+    let night = Hours::new("22:00 - 06:00").unwrap();
+    assert!(night.contains(time("23:00")));
+    assert!(night.contains(time("05:59")));
+    assert!(!night.contains(time("12:00")));
+    assert_eq!(night.to_string(), "22:00-06:00");
+
+    assert!(Hours::new("evenings").is_err());
+    assert!(Hours::new("25:00-06:00").is_err());
+    assert!(Hours::new("08:00-08:00").is_err());
+}
+
+#[test]
+fn test_reserve() {
+    let now = Instant::now();
+    let limit = Limit {
+        rate: Rate(1000),
+        hours: None,
+        free_at: Mutex::new(now),
+    };
+
+    // Nothing sent yet, so the first chunk goes straight away, and the next waits for it
+    assert_eq!(limit.reserve(500, now), Duration::ZERO);
+    assert_eq!(limit.reserve(500, now), Duration::from_millis(500));
+    assert_eq!(limit.reserve(1000, now), Duration::from_secs(1));
+
+    // Idle time isn't saved up for later
+    let later = now + Duration::from_secs(10);
+    assert_eq!(limit.reserve(1000, later), Duration::ZERO);
+    assert_eq!(limit.reserve(1000, later), Duration::from_secs(1));
+}
+
+#[test]
+fn test_unlimited() {
+    let start = Instant::now();
+    Throttle::default().wait(usize::MAX);
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
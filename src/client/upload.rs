@@ -1,75 +1,128 @@
+#[cfg(test)]
+mod tests;
+
+use super::observer::{TransferEvent, TransferObserver};
+use super::throttle::Throttle;
 use super::{ClientError, Result};
 
-use std::fs::File;
-use std::io::{Read, Write};
+use std::io::{ErrorKind, Read, Result as IoResult, Write};
 use std::sync::mpsc;
 use std::thread;
 
-use indicatif::{ProgressBar, ProgressStyle};
-use ssh2::Channel;
-
-// Buffer size for file transfers
-const BUF_SIZE: usize = 1024 * 4;
-
-pub(super) fn handle_upload(mut local_file: File, mut out_chan: Channel, size: u64) -> Result<()> {
-    let (tx, rx) = mpsc::channel();
+use sha2::{Digest, Sha256};
+
+/// Default buffer size for file transfers; SFTP waits for each write to be acknowledged, so this
+/// needs to be large enough to keep the link busy
+pub const DEFAULT_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Stream the remainder of the local file into the writer, which has already been positioned
+/// `offset` bytes in. The writer is handed back once all the data has been written so that the
+/// caller can close it off in whatever way the underlying transport requires.
+///
+/// If a hasher is provided, all data sent is also fed into it, and it's handed back alongside
+/// the writer. Data is only sent as fast as the throttle allows, and the observer is told about
+/// each chunk as it's sent.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn handle_upload<R: Read + Send + 'static, W: Write>(
+    local_file: R,
+    mut out: W,
+    mut hasher: Option<Sha256>,
+    offset: u64,
+    buffer_size: usize,
+    throttle: &Throttle,
+    observer: &dyn TransferObserver,
+    label: &str,
+) -> Result<(W, Option<Sha256>)> {
+    let mut sent = offset;
+
+    pipe(local_file, &mut out, buffer_size, |chunk| {
+        throttle.wait(chunk.len());
+        if let Some(h) = hasher.as_mut() {
+            h.update(chunk);
+        }
 
-    let sub = thread::spawn(move || -> Result<()> {
-        let mut buf: [u8; BUF_SIZE] = [0; BUF_SIZE];
+        let bytes = chunk.len() as u64;
+        sent += bytes;
+        observer.notify(&TransferEvent::Progress {
+            file: label,
+            bytes,
+            sent,
+        });
+    })?;
 
-        loop {
-            let n = local_file.read(&mut buf)?;
-            if n == 0 {
-                break;
-            }
-
-            let out_buf = &buf[0..n];
-            consume_buffer(&mut out_chan, out_buf)?;
+    Ok((out, hasher))
+}
 
-            let _ = tx.send(n);
+/// Copy everything from the reader into the writer, returning how many bytes were copied.
+///
+/// Reading happens on another thread, filling one buffer while the other is being written, so
+/// the writer never has to wait on the disk. `on_chunk` is given each chunk just before it's
+/// written.
+pub(super) fn pipe<R, W, F>(
+    mut reader: R,
+    out: &mut W,
+    buffer_size: usize,
+    mut on_chunk: F,
+) -> Result<u64>
+where
+    R: Read + Send + 'static,
+    W: Write + ?Sized,
+    F: FnMut(&[u8]),
+{
+    // Full buffers go to the writer, and are sent back to be refilled once written
+    let (full_tx, full_rx) = mpsc::sync_channel::<IoResult<(Vec<u8>, usize)>>(1);
+    let (empty_tx, empty_rx) = mpsc::channel::<Vec<u8>>();
+    for _ in 0..2 {
+        empty_tx
+            .send(vec![0; buffer_size.max(1)])
+            .map_err(|_| ClientError::Thread)?;
+    }
 
-            if n < BUF_SIZE {
+    let reader_thread = thread::spawn(move || {
+        for mut buf in empty_rx {
+            let res = match fill(&mut reader, &mut buf) {
+                Ok(0) => break,
+                Ok(n) => Ok((buf, n)),
+                Err(e) => Err(e),
+            };
+            let failed = res.is_err();
+
+            // The writer has given up if it's stopped listening
+            if full_tx.send(res).is_err() || failed {
                 break;
             }
         }
-
-        out_chan.send_eof()?;
-        out_chan.wait_eof()?;
-        out_chan.close()?;
-        out_chan.wait_close()?;
-        Ok(())
     });
 
-    // TODO: Decouple user display with the low-level logic of transferring the data; probably a
-    // better way to do this is to return the channel and pass it up to a higher level
-    let bar = ProgressBar::new(size).with_style(
-        ProgressStyle::with_template(
-            "{wide_bar:.green/blue} {eta} left ({bytes_per_sec}) {percent}% {msg:.green} ",
-        )
-        .unwrap(),
-    );
-    for packet in rx {
-        if let Ok(p) = packet.try_into() {
-            bar.inc(p);
-        }
-    }
-
-    sub.join().map_err(|_| ClientError::Thread)??;
-    bar.finish_with_message("OK");
+    let mut total: u64 = 0;
+    for chunk in &full_rx {
+        let (buf, n) = chunk?;
 
-    Ok(())
-}
+        on_chunk(&buf[..n]);
+        out.write_all(&buf[..n])?;
+        total += n as u64;
 
-// Completely consume the buffer, allowing the writer to backpressure where needed
-fn consume_buffer(writer: &mut dyn Write, buf: &[u8]) -> Result<()> {
-    let mut total: usize = 0;
+        // Only fails once the reader is done, and no longer needs it
+        let _ = empty_tx.send(buf);
+    }
 
-    loop {
-        let written = writer.write(&buf[total..])?;
-        total += written;
+    reader_thread.join().map_err(|_| ClientError::Thread)?;
+    Ok(total)
+}
 
-        if total == buf.len() {
-            return Ok(());
+// Read until the buffer is full or the reader runs out. A single read can return less than was
+// asked for without having reached the end, so this is the only way to be sure we have.
+fn fill<R: Read>(reader: &mut R, buf: &mut [u8]) -> IoResult<usize> {
+    let mut filled = 0;
+
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
         }
     }
+
+    Ok(filled)
 }
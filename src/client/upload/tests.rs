@@ -0,0 +1,198 @@
+use super::*;
+
+use std::io::{self, Cursor};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::client::observer::NoOp;
+
+// Returns no more than a few bytes from each read, and is sometimes interrupted, like a slow
+// network filesystem might be
+struct ShortReader {
+    data: Cursor<Vec<u8>>,
+    max_read: usize,
+    reads: usize,
+}
+
+impl ShortReader {
+    fn new(data: Vec<u8>, max_read: usize) -> ShortReader {
+        ShortReader {
+            data: Cursor::new(data),
+            max_read,
+            reads: 0,
+        }
+    }
+}
+
+impl Read for ShortReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reads += 1;
+        if self.reads.is_multiple_of(5) {
+            return Err(io::Error::new(ErrorKind::Interrupted, "interrupted"));
+        }
+
+        let len = buf.len().min(self.max_read);
+        self.data.read(&mut buf[..len])
+    }
+}
+
+struct BrokenReader;
+
+impl Read for BrokenReader {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        Err(io::Error::other("disk on fire"))
+    }
+}
+
+struct BrokenWriter;
+
+impl Write for BrokenWriter {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Ok(0)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn data(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+#[test]
+fn test_pipe_short_reads() {
+    let expected = data(20_000);
+
+    for buffer_size in [1, 7, 4096, 65536, 1024 * 1024] {
+        for max_read in [1, 13, 1000, 20_000] {
+            let mut out = vec![];
+            let mut seen = 0;
+
+            let reader = ShortReader::new(expected.clone(), max_read);
+            let copied = pipe(reader, &mut out, buffer_size, |c| seen += c.len()).unwrap();
+
+            assert_eq!(copied, expected.len() as u64);
+            assert_eq!(seen, expected.len());
+            assert!(
+                out == expected,
+                "buffer {}, reads of {}",
+                buffer_size,
+                max_read
+            );
+        }
+    }
+}
+
+#[test]
+fn test_pipe_fills_buffers() {
+    let mut chunks = vec![];
+    let reader = ShortReader::new(data(10_000), 100);
+    pipe(reader, &mut io::sink(), 4096, |c| chunks.push(c.len())).unwrap();
+
+    assert_eq!(chunks, vec![4096, 4096, 1808]);
+}
+
+#[test]
+fn test_pipe_empty() {
+    let mut out = vec![];
+    let copied = pipe(io::empty(), &mut out, 4096, |_| panic!("no data")).unwrap();
+
+    assert_eq!(copied, 0);
+    assert!(out.is_empty());
+}
+
+#[test]
+fn test_pipe_errors() {
+    let res = pipe(BrokenReader, &mut io::sink(), 4096, |_| ());
+    assert!(matches!(res, Err(ClientError::Io(e)) if e.kind() == ErrorKind::Other));
+
+    let reader = Cursor::new(data(100_000));
+    let res = pipe(reader, &mut BrokenWriter, 4096, |_| ());
+    assert!(matches!(res, Err(ClientError::Io(e)) if e.kind() == ErrorKind::WriteZero));
+}
+
+// Run with `cargo test --release -- --ignored --nocapture bench_pipe`
+#[test]
+#[ignore]
+fn bench_pipe() {
+    let len: u64 = 1024 * 1024 * 1024;
+
+    for buffer_size in [4096, 64 * 1024, DEFAULT_BUFFER_SIZE, 8 * 1024 * 1024] {
+        let start = Instant::now();
+        let copied = pipe(
+            io::repeat(1).take(len),
+            &mut io::sink(),
+            buffer_size,
+            |_| (),
+        )
+        .unwrap();
+        let elapsed = start.elapsed();
+
+        assert_eq!(copied, len);
+        println!(
+            "{:>8} byte buffer: {:.0} MB/s",
+            buffer_size,
+            len as f64 / elapsed.as_secs_f64() / 1e6
+        );
+    }
+}
+
+// Remembers how much progress it was told about
+#[derive(Default)]
+struct Recorder {
+    progress: Mutex<Vec<(u64, u64)>>,
+}
+
+impl TransferObserver for Recorder {
+    fn notify(&self, event: &TransferEvent) {
+        if let TransferEvent::Progress { file, bytes, sent } = event {
+            assert_eq!(*file, "S01 E01.mkv");
+            self.progress.lock().unwrap().push((*bytes, *sent));
+        }
+    }
+}
+
+#[test]
+fn test_handle_upload_reports_progress() {
+    let observer = Recorder::default();
+    let reader = ShortReader::new(data(10_000), 100);
+
+    let (out, hasher) = handle_upload(
+        reader,
+        vec![],
+        Some(Sha256::new()),
+        500,
+        4096,
+        &Throttle::default(),
+        &observer,
+        "S01 E01.mkv",
+    )
+    .unwrap();
+
+    assert!(out == data(10_000));
+    assert_eq!(hasher.unwrap().finalize(), Sha256::digest(data(10_000)));
+    assert_eq!(
+        observer.progress.into_inner().unwrap(),
+        vec![(4096, 4596), (4096, 8692), (1808, 10_500)]
+    );
+}
+
+#[test]
+fn test_handle_upload_without_observer() {
+    let reader = Cursor::new(data(1000));
+    let (out, hasher) = handle_upload(
+        reader,
+        vec![],
+        None,
+        0,
+        4096,
+        &Throttle::default(),
+        &NoOp,
+        "a.mkv",
+    )
+    .unwrap();
+
+    assert!(out == data(1000));
+    assert!(hasher.is_none());
+}
@@ -3,19 +3,64 @@ mod tests;
 
 use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::io::Error as IoError;
+use std::path::{Path, PathBuf};
 
 use regex::{Captures, Regex};
 use serde::Deserialize;
 use serde_inline_default::serde_inline_default;
+use thiserror::Error;
 use toml;
 
-use crate::local::FailureAction;
+use crate::client::observer::ProgressOutput;
+use crate::client::throttle::{Hours, Rate};
+use crate::client::upload::DEFAULT_BUFFER_SIZE;
+use crate::diff::{ExistingAction, UpgradeAction};
+use crate::episode::naming::NamingTemplate;
+use crate::local::{AfterUpload, FailureAction};
 
 const TMDB_HOST: &str = "api.themoviedb.org";
 
+// Anything smaller makes for painfully slow transfers
+const MIN_BUFFER_SIZE: usize = 4096;
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Couldn't find config in any of the following locations: {0:?}")]
+    NotFound(Vec<&'static str>),
+    #[error("Couldn't read config file {0}: {1}")]
+    Unreadable(PathBuf, IoError),
+    #[error("Invalid config file: {0}")]
+    Invalid(#[from] toml::de::Error),
+    #[error("Neither privkey nor password specified in config, you must provide one!")]
+    MissingAuth,
+    #[error("Both local and remote movie_dir must be set to sync films!")]
+    IncompleteMovies,
+    #[error("archive_dir must be set to archive uploaded episodes!")]
+    MissingArchiveDir,
+    #[error("Transfer concurrency must be at least 1!")]
+    BadConcurrency,
+    #[error("Transfer buffer_size must be at least {MIN_BUFFER_SIZE} bytes!")]
+    BadBufferSize,
+    #[error("Watch poll_interval and settle_time must be at least 1 second!")]
+    BadWatchTimes,
+    #[error("Watch min_certainty must be between 0 and 1!")]
+    BadCertainty,
+    #[error("TMDB token must be provided if TMDB is enabled!")]
+    MissingTmdbToken,
+    #[error("episode_titles is enabled, but episode_template has no {{title}} to put them in!")]
+    UnusedEpisodeTitles,
+    #[error("max_rate_hours is set, but there's no max_rate to limit uploads to during them!")]
+    MissingMaxRate,
+    #[error("progress_file must be set to write upload progress as JSON!")]
+    MissingProgressFile,
+}
+
 #[derive(Deserialize, Debug)]
 pub(super) struct Config {
+    // The file this config was read from
+    #[serde(skip)]
+    pub source: PathBuf,
     pub local: Local,
     pub remote: Remote,
     pub validation: Validation,
@@ -24,11 +69,27 @@ pub(super) struct Config {
     pub log: Logging,
     #[serde(default = "default_ui")]
     pub ui: Ui,
+    #[serde(default = "default_transfer")]
+    pub transfer: Transfer,
+    #[serde(default = "default_watch")]
+    pub watch: Watch,
 }
 
+#[serde_inline_default]
 #[derive(Deserialize, Debug)]
 pub(super) struct Local {
     pub tv_dir: String,
+    // Films are only synced if this and remote.movie_dir are both set
+    pub movie_dir: Option<String>,
+    // How many levels of folders inside each show folder to look for episodes in, e.g. season
+    // folders and release folders
+    #[serde_inline_default(2)]
+    pub scan_depth: usize,
+    // What to do with episodes once they've been uploaded successfully
+    #[serde_inline_default(AfterUpload::Leave)]
+    pub after_upload: AfterUpload,
+    // Where episodes are moved to with after_upload = "archive"
+    pub archive_dir: Option<String>,
 }
 
 #[serde_inline_default]
@@ -45,6 +106,11 @@ pub(super) struct Remote {
     pub privkey: Option<String>,
     #[serde_inline_default("/usr/store/tv/".to_string())]
     pub tv_dir: String,
+    #[serde_inline_default(None)]
+    pub movie_dir: Option<String>,
+    // Where episodes go within tv_dir, e.g. "{show}/Season {season:02}/E{episode:02}.{ext}"
+    #[serde_inline_default(NamingTemplate::default())]
+    pub episode_template: NamingTemplate,
 }
 
 #[serde_inline_default]
@@ -56,11 +122,31 @@ pub(super) struct Validation {
     #[serde_inline_default(FailureAction::Skip)]
     pub on_failure: FailureAction,
 
+    // What to do when an episode is already on the remote in another form
+    #[serde_inline_default(ExistingAction::Skip)]
+    pub on_existing: ExistingAction,
+
+    // What to do when a local file is a better version of an episode already on the remote
+    #[serde_inline_default(UpgradeAction::Skip)]
+    pub on_upgrade: UpgradeAction,
+
     #[serde(default = "default_tmdb")]
     pub tmdb: Tmdb,
 
     #[serde_inline_default(true)]
     pub prompt_confirmation: bool,
+
+    // With on_failure = "prompt", ask which show was meant when less certain than this (0-1)
+    #[serde_inline_default(0.5)]
+    pub prompt_threshold: f64,
+
+    // How many suggested shows to offer when prompting
+    #[serde_inline_default(5)]
+    pub prompt_candidates: usize,
+
+    // Where to remember approved mappings of local folder names to TV shows
+    #[serde(default = "default_aliases_file")]
+    pub aliases_file: PathBuf,
 }
 
 #[derive(Deserialize, Debug)]
@@ -76,6 +162,59 @@ pub(super) struct Ui {
     // to see the final output before the terminal window disappears
     #[serde_inline_default(false)]
     pub block_closing: bool,
+
+    // How to show upload progress: "bars", "json" lines for scripts, or "none"
+    #[serde_inline_default(ProgressOutput::Bars)]
+    pub progress: ProgressOutput,
+
+    // Where to write JSON progress events; required for "json"
+    #[serde(default)]
+    pub progress_file: Option<String>,
+}
+
+#[serde_inline_default]
+#[derive(Deserialize, Debug)]
+pub(super) struct Transfer {
+    // Whether to checksum uploaded files before moving them into place
+    #[serde_inline_default(false)]
+    pub verify: bool,
+
+    // How many files to upload at once, each over its own SSH session
+    #[serde_inline_default(1)]
+    pub concurrency: usize,
+
+    // Only print what would be uploaded, without changing anything on the remote
+    #[serde_inline_default(false)]
+    pub dry_run: bool,
+
+    // How many bytes of a file to read and write at a time
+    #[serde_inline_default(DEFAULT_BUFFER_SIZE)]
+    pub buffer_size: usize,
+
+    // The most to upload per second across all files, e.g. "5MB/s"; unlimited if not set
+    #[serde(default)]
+    pub max_rate: Option<Rate>,
+
+    // Only apply max_rate during these hours, e.g. "08:00-23:00"
+    #[serde(default)]
+    pub max_rate_hours: Option<Hours>,
+}
+
+#[serde_inline_default]
+#[derive(Deserialize, Debug)]
+pub(super) struct Watch {
+    // How often to look for new files, in seconds
+    #[serde_inline_default(60)]
+    pub poll_interval: u64,
+
+    // How long a file's size must stay the same before it's considered complete, in seconds
+    #[serde_inline_default(120)]
+    pub settle_time: u64,
+
+    // How certain a show match must be (0-1) to sync it unattended; anything less is left for a
+    // manual sync to confirm
+    #[serde_inline_default(0.8)]
+    pub min_certainty: f64,
 }
 
 #[serde_inline_default]
@@ -107,6 +246,35 @@ pub(super) struct Tmdb {
     pub host: String,
     #[serde_inline_default(None)]
     pub token: Option<String>,
+    // Whether to look up episode titles and include them in remote filenames
+    #[serde_inline_default(false)]
+    pub episode_titles: bool,
+}
+
+/// Settings given on the command line, which take precedence over the config file
+#[derive(Debug, Default)]
+pub(super) struct Overrides {
+    pub skip_confirmation: bool,
+    pub progress: Option<ProgressOutput>,
+    pub progress_file: Option<String>,
+    pub tv_dir: Option<String>,
+}
+
+impl Overrides {
+    fn apply(self, conf: &mut Config) {
+        if self.skip_confirmation {
+            conf.validation.prompt_confirmation = false;
+        }
+        if let Some(progress) = self.progress {
+            conf.ui.progress = progress;
+        }
+        if self.progress_file.is_some() {
+            conf.ui.progress_file = self.progress_file;
+        }
+        if let Some(dir) = self.tv_dir {
+            conf.local.tv_dir = dir;
+        }
+    }
 }
 
 // Non-inline defaults
@@ -127,10 +295,38 @@ fn default_local_log_path() -> PathBuf {
     PathBuf::from(sub_vars("${HOME}/.rusttv/events/"))
 }
 
+// Validation defaults
+fn default_aliases_file() -> PathBuf {
+    PathBuf::from(sub_vars("${HOME}/.rusttv/aliases.toml"))
+}
+
 // UI defaults
 fn default_ui() -> Ui {
     Ui {
         block_closing: false,
+        progress: ProgressOutput::Bars,
+        progress_file: None,
+    }
+}
+
+// Transfer defaults
+fn default_transfer() -> Transfer {
+    Transfer {
+        verify: false,
+        concurrency: 1,
+        dry_run: false,
+        buffer_size: DEFAULT_BUFFER_SIZE,
+        max_rate: None,
+        max_rate_hours: None,
+    }
+}
+
+// Watch defaults
+fn default_watch() -> Watch {
+    Watch {
+        poll_interval: 60,
+        settle_time: 120,
+        min_certainty: 0.8,
     }
 }
 
@@ -141,6 +337,7 @@ fn default_tmdb() -> Tmdb {
         protocol: "https".to_string(),
         host: TMDB_HOST.to_string(),
         token: None,
+        episode_titles: false,
     }
 }
 
@@ -172,24 +369,27 @@ fn sub_vars(line: &str) -> String {
     result
 }
 
-fn read_raw() -> String {
-    let config_files: [&str; 3] = [
-        "${HOME}/.rusttv/config.toml",
-        "/usr/share/rusttv/config.toml",
-        "config.toml",
-    ];
+const CONFIG_FILES: [&str; 3] = [
+    "${HOME}/.rusttv/config.toml",
+    "/usr/share/rusttv/config.toml",
+    "config.toml",
+];
+
+fn read_raw(path: Option<&Path>) -> Result<(PathBuf, String), ConfigError> {
+    if let Some(p) = path {
+        let data =
+            fs::read_to_string(p).map_err(|e| ConfigError::Unreadable(p.to_path_buf(), e))?;
+        return Ok((p.to_path_buf(), data));
+    }
 
-    for f in config_files {
+    for f in CONFIG_FILES {
         let resolved = sub_vars(f);
 
         if let Ok(data) = fs::read_to_string(&resolved) {
-            return data;
+            return Ok((PathBuf::from(resolved), data));
         }
     }
-    panic!(
-        "Couldn't find config in any of the following locations: {:?}",
-        config_files
-    );
+    Err(ConfigError::NotFound(CONFIG_FILES.to_vec()))
 }
 
 macro_rules! sub_vars {
@@ -204,23 +404,76 @@ macro_rules! sub_vars_opt {
     };
 }
 
-pub(super) fn read() -> Config {
-    let raw = read_raw();
-    let mut conf: Config = toml::from_str(&raw).expect("Invalid config file!");
+// Overrides are applied before validating, so they can make up for anything missing from the file
+fn parse(raw: &str, overrides: Overrides) -> Result<Config, ConfigError> {
+    let mut conf: Config = toml::from_str(raw)?;
 
     // Substitute env vars in selected fields
     sub_vars!(conf.local.tv_dir);
     sub_vars!(conf.remote.tv_dir);
+    sub_vars_opt!(conf.local.movie_dir);
+    sub_vars_opt!(conf.local.archive_dir);
+    sub_vars_opt!(conf.ui.progress_file);
+    sub_vars_opt!(conf.remote.movie_dir);
     sub_vars_opt!(conf.remote.privkey);
     sub_vars_opt!(conf.validation.tmdb.token);
+    conf.validation.aliases_file =
+        PathBuf::from(sub_vars(&conf.validation.aliases_file.to_string_lossy()));
+    overrides.apply(&mut conf);
 
     if conf.remote.privkey.is_none() && conf.remote.password.is_none() {
-        panic!("Neither privkey nor password specified in config, you must provide one!")
+        return Err(ConfigError::MissingAuth);
+    }
+
+    if conf.local.movie_dir.is_some() != conf.remote.movie_dir.is_some() {
+        return Err(ConfigError::IncompleteMovies);
+    }
+
+    if conf.local.after_upload == AfterUpload::Archive && conf.local.archive_dir.is_none() {
+        return Err(ConfigError::MissingArchiveDir);
+    }
+
+    if conf.watch.poll_interval == 0 || conf.watch.settle_time == 0 {
+        return Err(ConfigError::BadWatchTimes);
+    }
+
+    if !(0.0..=1.0).contains(&conf.watch.min_certainty) {
+        return Err(ConfigError::BadCertainty);
+    }
+
+    if conf.transfer.concurrency == 0 {
+        return Err(ConfigError::BadConcurrency);
+    }
+
+    if conf.transfer.buffer_size < MIN_BUFFER_SIZE {
+        return Err(ConfigError::BadBufferSize);
+    }
+
+    if conf.ui.progress == ProgressOutput::Json && conf.ui.progress_file.is_none() {
+        return Err(ConfigError::MissingProgressFile);
+    }
+
+    if conf.transfer.max_rate_hours.is_some() && conf.transfer.max_rate.is_none() {
+        return Err(ConfigError::MissingMaxRate);
     }
 
     if conf.validation.tmdb.enabled && conf.validation.tmdb.token.is_none() {
-        panic!("TMDB token must be provided if TMDB is enabled!")
+        return Err(ConfigError::MissingTmdbToken);
+    }
+
+    if conf.validation.tmdb.episode_titles && !conf.remote.episode_template.has_title() {
+        return Err(ConfigError::UnusedEpisodeTitles);
     }
 
-    conf
+    Ok(conf)
+}
+
+/// Read config from the given path, or from the first of the default locations which exists,
+/// with any settings given on the command line in place of those in the file
+pub(super) fn read(path: Option<&Path>, overrides: Overrides) -> Result<Config, ConfigError> {
+    let (source, raw) = read_raw(path)?;
+    let mut conf = parse(&raw, overrides)?;
+    conf.source = source;
+
+    Ok(conf)
 }
@@ -27,3 +27,140 @@ fn sub_vars_multiple_replacements() {
     let actual = sub_vars("${RUSTTV_TEST2A}-${RUSTTV_TEST2B}");
     assert_eq!(actual, expected);
 }
+
+const MINIMAL_CONFIG: &str = r#"
+[remote]
+host = "rusttv"
+password = "hunter2"
+
+[local]
+tv_dir = "${RUSTTV_TEST3}/tv/"
+
+[validation]
+
+[osmc]
+host = "rusttv"
+"#;
+
+#[test]
+fn parse_minimal_config() {
+    env::set_var("RUSTTV_TEST3", "/home/calculon");
+
+    let conf = parse(MINIMAL_CONFIG, Overrides::default()).unwrap();
+    assert_eq!(conf.local.tv_dir, "/home/calculon/tv/");
+    assert_eq!(conf.remote.port, 22);
+    assert_eq!(conf.transfer.concurrency, 1);
+    assert_eq!(conf.watch.settle_time, 120);
+    assert_eq!(conf.transfer.buffer_size, DEFAULT_BUFFER_SIZE);
+}
+
+#[test]
+fn parse_requires_auth() {
+    let raw = MINIMAL_CONFIG.replace("password = \"hunter2\"", "");
+    let actual = parse(&raw, Overrides::default()).unwrap_err();
+    assert!(matches!(actual, ConfigError::MissingAuth));
+}
+
+#[test]
+fn parse_requires_archive_dir() {
+    let raw = MINIMAL_CONFIG.replace("[validation]", "after_upload = \"archive\"\n\n[validation]");
+    let actual = parse(&raw, Overrides::default()).unwrap_err();
+    assert!(matches!(actual, ConfigError::MissingArchiveDir));
+
+    let raw = raw.replace(
+        "after_upload",
+        "archive_dir = \"/tmp/archive\"\nafter_upload",
+    );
+    let conf = parse(&raw, Overrides::default()).unwrap();
+    assert_eq!(conf.local.after_upload, AfterUpload::Archive);
+}
+
+#[test]
+fn parse_rejects_zero_watch_times() {
+    for setting in ["poll_interval = 0", "settle_time = 0"] {
+        let raw = format!("{}\n[watch]\n{}\n", MINIMAL_CONFIG, setting);
+        assert!(matches!(
+            parse(&raw, Overrides::default()),
+            Err(ConfigError::BadWatchTimes)
+        ));
+    }
+}
+
+#[test]
+fn parse_max_rate() {
+    let raw = format!(
+        "{}\n[transfer]\nmax_rate = \"5MB/s\"\nmax_rate_hours = \"08:00-23:00\"\n",
+        MINIMAL_CONFIG
+    );
+    let conf = parse(&raw, Overrides::default()).unwrap();
+    assert_eq!(
+        conf.transfer.max_rate.map(|r| r.bytes_per_sec()),
+        Some(5_000_000)
+    );
+    assert_eq!(
+        conf.transfer.max_rate_hours.map(|h| h.to_string()),
+        Some("08:00-23:00".into())
+    );
+
+    let no_rate = raw.replace("max_rate = \"5MB/s\"\n", "");
+    assert!(matches!(
+        parse(&no_rate, Overrides::default()),
+        Err(ConfigError::MissingMaxRate)
+    ));
+
+    let no_hours = raw.replace("08:00-23:00", "08:00-08:00");
+    assert!(matches!(
+        parse(&no_hours, Overrides::default()),
+        Err(ConfigError::Invalid(_))
+    ));
+
+    let raw = raw.replace("5MB/s", "very fast");
+    assert!(matches!(
+        parse(&raw, Overrides::default()),
+        Err(ConfigError::Invalid(_))
+    ));
+}
+
+#[test]
+fn parse_rejects_unused_episode_titles() {
+    let raw = format!(
+        "{}\n[validation.tmdb]\nepisode_titles = true\n",
+        MINIMAL_CONFIG
+    )
+    .replace("[validation]\n", "");
+    assert!(
+        parse(&raw, Overrides::default())
+            .unwrap()
+            .validation
+            .tmdb
+            .episode_titles
+    );
+
+    let raw = raw.replace(
+        "password = \"hunter2\"",
+        "password = \"hunter2\"\nepisode_template = \"{show}/S{season:02}E{episode:02}.{ext}\"",
+    );
+    let actual = parse(&raw, Overrides::default()).unwrap_err();
+    assert!(matches!(actual, ConfigError::UnusedEpisodeTitles));
+}
+
+#[test]
+fn parse_json_progress_needs_file() {
+    let raw = format!("{}\n[ui]\nprogress = \"json\"\n", MINIMAL_CONFIG);
+    assert!(matches!(
+        parse(&raw, Overrides::default()),
+        Err(ConfigError::MissingProgressFile)
+    ));
+
+    // Given on the command line instead
+    let overrides = Overrides {
+        progress_file: Some("/dev/fd/4".to_string()),
+        ..Overrides::default()
+    };
+    let conf = parse(&raw, overrides).unwrap();
+    assert_eq!(conf.ui.progress_file.as_deref(), Some("/dev/fd/4"));
+
+    let raw = raw.replace("[ui]", "[ui]\nprogress_file = \"/dev/fd/3\"");
+    let conf = parse(&raw, Overrides::default()).unwrap();
+    assert_eq!(conf.ui.progress_file.as_deref(), Some("/dev/fd/3"));
+}
@@ -0,0 +1,200 @@
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashMap;
+use std::fs;
+
+use dialoguer::{Confirm, Select};
+use serde::Deserialize;
+
+use crate::client::RemoteFile;
+use crate::episode::{resolution, Episode};
+
+/// What to do when an episode is already on the remote in another form, e.g. as an .mkv where we
+/// have an .mp4
+#[derive(Clone, Copy, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExistingAction {
+    Skip,
+    // Upload it alongside the existing file
+    Alternate,
+    // Upload it in place of the existing file, but only if it's bigger
+    ReplaceIfLarger,
+    // Ask the user which of the above to do
+    Prompt,
+}
+
+/// What to do when a local file is a better version of an episode already on the remote
+#[derive(Clone, Copy, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum UpgradeAction {
+    Skip,
+    // Ask the user whether to replace it
+    Prompt,
+    Replace,
+}
+
+/// How to treat episodes which are already on the remote in some form
+#[derive(Clone, Copy, Debug)]
+pub struct DiffOptions {
+    pub on_existing: ExistingAction,
+    pub on_upgrade: UpgradeAction,
+}
+
+impl DiffOptions {
+    /// The same options, but skipping anything we'd otherwise ask the user about, for when
+    /// nobody is there to answer
+    pub fn unattended(self) -> DiffOptions {
+        DiffOptions {
+            on_existing: match self.on_existing {
+                ExistingAction::Prompt => ExistingAction::Skip,
+                action => action,
+            },
+            on_upgrade: match self.on_upgrade {
+                UpgradeAction::Prompt => UpgradeAction::Skip,
+                action => action,
+            },
+        }
+    }
+}
+
+// What we've decided to do with a single local file
+#[derive(Debug, PartialEq)]
+enum Decision {
+    Skip,
+    Upload,
+    Replace(String),
+}
+
+fn local_size(ep: &Episode) -> Option<u64> {
+    fs::metadata(&ep.local_path).ok().map(|m| m.len())
+}
+
+fn prompt_existing(ep: &Episode, existing: &RemoteFile) -> Decision {
+    let items = [
+        "Skip it",
+        "Upload it alongside the existing file",
+        "Replace the existing file",
+    ];
+
+    let choice = Select::new()
+        .with_prompt(format!(
+            "{}: {} is already on the remote as \"{}\" ({} MB). What should we do?",
+            ep.show_name,
+            ep.remote_filename(),
+            existing.path,
+            existing.size / 1_000_000
+        ))
+        .items(&items)
+        .default(0)
+        .interact();
+
+    match choice {
+        Ok(1) => Decision::Upload,
+        Ok(2) => Decision::Replace(existing.path.clone()),
+        _ => Decision::Skip,
+    }
+}
+
+fn local_resolution(ep: &Episode) -> Option<u32> {
+    resolution(ep.local_path.file_name()?.to_str()?)
+}
+
+// A local video is better than the remote copy if it has a higher resolution according to the
+// quality tags in both filenames or, if that's not known, if it's bigger
+fn is_upgrade(ep: &Episode, existing: &RemoteFile) -> bool {
+    if ep.is_subtitle() {
+        return false;
+    }
+
+    match (local_resolution(ep), resolution(&existing.path)) {
+        (Some(local), Some(remote)) if local != remote => local > remote,
+        _ => local_size(ep).is_some_and(|size| size > existing.size),
+    }
+}
+
+fn prompt_upgrade(ep: &Episode, existing: &RemoteFile) -> Decision {
+    let replace = Confirm::new()
+        .with_prompt(format!(
+            "{}: {} looks like a better version of \"{}\" on the remote ({} MB). Replace it?",
+            ep.show_name,
+            ep.local_path.display(),
+            existing.path,
+            existing.size / 1_000_000
+        ))
+        .default(false)
+        .interact()
+        .unwrap_or(false);
+
+    if replace {
+        Decision::Replace(existing.path.clone())
+    } else {
+        Decision::Skip
+    }
+}
+
+fn decide(ep: &Episode, remote_files: &[RemoteFile], options: &DiffOptions) -> Decision {
+    if let Some(existing) = remote_files.iter().find(|f| ep.covered_by(&f.path)) {
+        // Replacing a file which also holds other episodes would lose them
+        if !is_upgrade(ep, existing) || !ep.same_episodes_as(&existing.path) {
+            return Decision::Skip;
+        }
+
+        return match options.on_upgrade {
+            UpgradeAction::Skip => Decision::Skip,
+            UpgradeAction::Prompt => prompt_upgrade(ep, existing),
+            UpgradeAction::Replace => Decision::Replace(existing.path.clone()),
+        };
+    }
+    let Some(existing) = remote_files.iter().find(|f| ep.variant_of(&f.path)) else {
+        return Decision::Upload;
+    };
+
+    match options.on_existing {
+        ExistingAction::Skip => Decision::Skip,
+        ExistingAction::Alternate => Decision::Upload,
+        ExistingAction::ReplaceIfLarger => match local_size(ep) {
+            Some(size) if size > existing.size && ep.same_episodes_as(&existing.path) => {
+                Decision::Replace(existing.path.clone())
+            }
+            _ => Decision::Skip,
+        },
+        ExistingAction::Prompt => prompt_existing(ep, existing),
+    }
+}
+
+// Decide whether to upload an episode, noting which remote file it replaces if any
+fn keep(ep: &mut Episode, remote_files: &[RemoteFile], options: &DiffOptions) -> bool {
+    match decide(ep, remote_files, options) {
+        Decision::Skip => false,
+        Decision::Upload => true,
+        Decision::Replace(path) => {
+            ep.replaces = Some(path);
+            true
+        }
+    }
+}
+
+/// Find the episodes which need uploading, given the files already in each show's remote folder.
+/// Subtitles missing from the remote are still uploaded if their video is already there.
+pub fn diff_eps(
+    local: Vec<Episode>,
+    remote: &HashMap<String, Vec<RemoteFile>>,
+    options: &DiffOptions,
+) -> Vec<Episode> {
+    local
+        .into_iter()
+        .flat_map(|mut ep| {
+            let Some(remote_files) = remote.get(&ep.show_name) else {
+                return vec![];
+            };
+
+            ep.subtitles.retain_mut(|s| keep(s, remote_files, options));
+            if keep(&mut ep, remote_files, options) {
+                vec![ep]
+            } else {
+                ep.subtitles
+            }
+        })
+        .collect()
+}
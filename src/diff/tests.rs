@@ -0,0 +1,185 @@
+use super::*;
+
+use crate::tests as utils;
+
+const SHOW: &str = "All My Circuits";
+
+// Sizes are read from disk, so each test (or case within one) writes its files to its own dir so
+// tests running in parallel don't trample each other's
+fn episode(dir: &str, filename: &str, size: usize) -> Episode {
+    let path = utils::create_path(&format!("diff/{}/{}", dir, filename));
+    fs::write(&path, vec![0; size]).unwrap();
+
+    let exts = vec!["mkv", "mp4", "srt"];
+    Episode::from(&path, filename, SHOW, 1.0, &exts).unwrap()
+}
+
+fn remote(files: &[(&str, u64)]) -> HashMap<String, Vec<RemoteFile>> {
+    let files = files
+        .iter()
+        .map(|(path, size)| RemoteFile {
+            path: path.to_string(),
+            size: *size,
+        })
+        .collect();
+
+    HashMap::from([(SHOW.to_string(), files)])
+}
+
+fn options(on_existing: ExistingAction) -> DiffOptions {
+    DiffOptions {
+        on_existing,
+        on_upgrade: UpgradeAction::Skip,
+    }
+}
+
+fn names(eps: &[Episode]) -> Vec<String> {
+    eps.iter().map(|e| e.remote_filename()).collect()
+}
+
+#[test]
+fn skips_present_episodes() {
+    let local = vec![
+        episode("skips_present", "S01E01.mkv", 10),
+        episode("skips_present", "S01E02.mkv", 10),
+    ];
+    let remote = remote(&[("Season 1/All My Circuits - S01E01.mkv", 10)]);
+
+    let actual = diff_eps(local, &remote, &options(ExistingAction::Alternate));
+    assert_eq!(names(&actual), vec!["S01 E02.mkv"]);
+}
+
+#[test]
+fn existing_in_another_form() {
+    let remote = remote(&[("S01 E01.mkv", 100)]);
+
+    let actual = diff_eps(
+        vec![episode("another_form/skip", "S01E01.mp4", 10)],
+        &remote,
+        &options(ExistingAction::Skip),
+    );
+    assert!(actual.is_empty());
+
+    let actual = diff_eps(
+        vec![episode("another_form/alternate", "S01E01.mp4", 10)],
+        &remote,
+        &options(ExistingAction::Alternate),
+    );
+    assert_eq!(names(&actual), vec!["S01 E01.mp4"]);
+    assert_eq!(actual[0].replaces, None);
+
+    let actual = diff_eps(
+        vec![episode("another_form/smaller", "S01E01.mp4", 10)],
+        &remote,
+        &options(ExistingAction::ReplaceIfLarger),
+    );
+    assert!(actual.is_empty());
+
+    let actual = diff_eps(
+        vec![episode("another_form/larger", "S01E01.mp4", 200)],
+        &remote,
+        &options(ExistingAction::ReplaceIfLarger),
+    );
+    assert_eq!(names(&actual), vec!["S01 E01.mp4"]);
+    assert_eq!(actual[0].replaces, Some("S01 E01.mkv".to_string()));
+}
+
+#[test]
+fn subtitles_uploaded_for_existing_video() {
+    let mut video = episode("subtitles", "S01E01.mkv", 10);
+    video.subtitles = vec![
+        episode("subtitles", "S01E01.en.srt", 1),
+        episode("subtitles", "S01E01.fr.srt", 1),
+    ];
+    let remote = remote(&[("S01 E01.mkv", 10), ("S01 E01.en.srt", 1)]);
+
+    let actual = diff_eps(vec![video], &remote, &options(ExistingAction::Skip));
+    assert_eq!(names(&actual), vec!["S01 E01.fr.srt"]);
+}
+
+#[test]
+fn upgrades_existing_episodes() {
+    let upgrade = |on_upgrade| DiffOptions {
+        on_existing: ExistingAction::Skip,
+        on_upgrade,
+    };
+    let remote = remote(&[("S01 E01.mkv", 100), ("S01 E02.720p.mkv", 1000)]);
+
+    // Not opted in
+    let actual = diff_eps(
+        vec![episode("upgrades/not_opted_in", "S01E01.mkv", 200)],
+        &remote,
+        &upgrade(UpgradeAction::Skip),
+    );
+    assert!(actual.is_empty());
+
+    // Bigger
+    let actual = diff_eps(
+        vec![episode("upgrades/bigger", "S01E01.mkv", 200)],
+        &remote,
+        &upgrade(UpgradeAction::Replace),
+    );
+    assert_eq!(actual[0].replaces, Some("S01 E01.mkv".to_string()));
+
+    // Smaller
+    let actual = diff_eps(
+        vec![episode("upgrades/smaller", "S01E01.mkv", 50)],
+        &remote,
+        &upgrade(UpgradeAction::Replace),
+    );
+    assert!(actual.is_empty());
+
+    // Higher resolution, even though it's smaller
+    let actual = diff_eps(
+        vec![episode("upgrades/resolution", "S01E02.1080p.mkv", 10)],
+        &remote,
+        &upgrade(UpgradeAction::Replace),
+    );
+    assert_eq!(actual[0].replaces, Some("S01 E02.720p.mkv".to_string()));
+}
+
+#[test]
+fn never_replaces_other_episodes() {
+    let remote = remote(&[("S02 E05-E06.mkv", 100), ("S02 E07-E08.mp4", 100)]);
+    let replacing = DiffOptions {
+        on_existing: ExistingAction::ReplaceIfLarger,
+        on_upgrade: UpgradeAction::Replace,
+    };
+
+    // Bigger, but replacing the remote file would lose E06 or E08
+    let actual = diff_eps(
+        vec![
+            episode("partial_overlap", "S02E05.mkv", 200),
+            episode("partial_overlap", "S02E07.mkv", 200),
+        ],
+        &remote,
+        &replacing,
+    );
+    assert!(actual.is_empty());
+
+    let actual = diff_eps(
+        vec![episode("same_episodes", "S02E05E06.mkv", 200)],
+        &remote,
+        &replacing,
+    );
+    assert_eq!(actual[0].replaces, Some("S02 E05-E06.mkv".to_string()));
+}
+
+#[test]
+fn test_unattended_never_prompts() {
+    let prompting = DiffOptions {
+        on_existing: ExistingAction::Prompt,
+        on_upgrade: UpgradeAction::Prompt,
+    }
+    .unattended();
+    assert_eq!(prompting.on_existing, ExistingAction::Skip);
+    assert_eq!(prompting.on_upgrade, UpgradeAction::Skip);
+
+    let decided = DiffOptions {
+        on_existing: ExistingAction::Alternate,
+        on_upgrade: UpgradeAction::Replace,
+    }
+    .unattended();
+    assert_eq!(decided.on_existing, ExistingAction::Alternate);
+    assert_eq!(decided.on_upgrade, UpgradeAction::Replace);
+}
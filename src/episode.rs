@@ -1,23 +1,58 @@
 #[cfg(test)]
 mod tests;
 
+pub mod naming;
+
 use std::cmp::Ordering;
 use std::fmt;
 use std::path::{Path, PathBuf};
 
+use chrono::NaiveDate;
 use console::Style;
 use regex::Regex;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use typed_path::Utf8UnixPathBuf;
 
-#[derive(Clone, Debug, Serialize)]
+use naming::NamingTemplate;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Episode {
     pub local_path: PathBuf,
     pub show_name: String,
     pub show_certainty: f64,
     pub season_num: u32,
     pub episode_num: u32,
+    // The last episode contained in the file, for files holding several episodes (S01E01E02)
+    #[serde(default)]
+    pub last_episode_num: Option<u32>,
+    // The original episode number for shows numbered from the first episode, like most anime
+    #[serde(default)]
+    pub absolute_num: Option<u32>,
+    // The air date of episodes of daily shows, which are named by date rather than episode
+    #[serde(default)]
+    pub air_date: Option<NaiveDate>,
     pub ext: String,
+    // The episode's title, if we've looked it up
+    #[serde(default)]
+    pub title: Option<String>,
+    // A remote file holding this episode in another form, relative to the show's folder, which
+    // this upload replaces
+    #[serde(default)]
+    pub replaces: Option<String>,
+    // Language and flags of a subtitle file, like ["en", "forced"]; empty for videos
+    #[serde(default)]
+    pub tags: Vec<String>,
+    // Subtitle files belonging to this video
+    #[serde(default)]
+    pub subtitles: Vec<Episode>,
+}
+
+// How an episode is identified by its filename
+#[derive(Debug, PartialEq)]
+enum EpisodeNumber {
+    Seasonal(u32, u32, Option<u32>),
+    Absolute(u32),
+    Dated(NaiveDate),
 }
 
 #[derive(Debug, PartialEq)]
@@ -26,12 +61,127 @@ pub enum ParseError {
     BadFilename,
     BadPath,
     BadExtension,
+    BadYear,
+}
+
+pub const SUBTITLE_EXTS: [&str; 5] = ["srt", "ass", "ssa", "sub", "vtt"];
+
+// Subtitle languages, as the ISO 639-1 code Kodi expects and other names they go by
+const LANGUAGES: [(&str, &[&str]); 20] = [
+    ("en", &["eng", "english"]),
+    ("fr", &["fre", "fra", "french"]),
+    ("de", &["ger", "deu", "german"]),
+    ("es", &["spa", "spanish"]),
+    ("it", &["ita", "italian"]),
+    ("pt", &["por", "portuguese"]),
+    ("nl", &["dut", "nld", "dutch"]),
+    ("sv", &["swe", "swedish"]),
+    ("no", &["nor", "norwegian"]),
+    ("da", &["dan", "danish"]),
+    ("fi", &["fin", "finnish"]),
+    ("pl", &["pol", "polish"]),
+    ("ru", &["rus", "russian"]),
+    ("ja", &["jpn", "japanese"]),
+    ("zh", &["chi", "zho", "chinese"]),
+    ("ko", &["kor", "korean"]),
+    ("ar", &["ara", "arabic"]),
+    ("el", &["gre", "ell", "greek"]),
+    ("tr", &["tur", "turkish"]),
+    ("he", &["heb", "hebrew"]),
+];
+
+/// Find the language and flags of a subtitle file from the end of its name, e.g.
+/// Show.S01E01.English.Forced.srt -> ["en", "forced"]. Hearing impaired subtitles may be marked
+/// SDH, CC or HI, which are all given as "sdh".
+pub fn subtitle_tags(filename: &str) -> Vec<String> {
+    let stem = filename
+        .rsplit_once('.')
+        .map(|(stem, _)| stem)
+        .unwrap_or(filename);
+
+    let mut lang = None;
+    let mut forced = false;
+    let mut sdh = false;
+
+    for token in stem.rsplit(['.', ' ', '_', '-', '[', ']', '(', ')']) {
+        let token = token.to_lowercase();
+        if token.is_empty() {
+            continue;
+        }
+
+        match token.as_str() {
+            "forced" => forced = true,
+            "sdh" | "cc" | "hi" => sdh = true,
+            t => match LANGUAGES
+                .iter()
+                .find(|(code, names)| *code == t || names.contains(&t))
+            {
+                Some((code, _)) if lang.is_none() => lang = Some(code.to_string()),
+                _ => break,
+            },
+        }
+    }
+
+    lang.into_iter()
+        .chain(forced.then(|| "forced".to_string()))
+        .chain(sdh.then(|| "sdh".to_string()))
+        .collect()
+}
+
+/// Find the season number from the name of a season folder, like "Season 2", "Series 02" or
+/// "S02"; specials are season 0
+pub fn season_from_folder(name: &str) -> Option<u32> {
+    let pattern = Regex::new(r"^(?i)(?:season|series|s)[\s\._\-]*([0-9]{1,2})$").unwrap();
+    let name = name.trim();
+
+    if name.eq_ignore_ascii_case("specials") {
+        return Some(0);
+    }
+
+    pattern
+        .captures(name)
+        .map(|caps| caps.get(1).unwrap().as_str().parse::<u32>().unwrap())
+}
+
+/// Find the vertical resolution of a video from quality tags in its filename, e.g.
+/// Show.S01E01.1080p.WEB.mkv -> 1080; 4K and UHD releases are 2160
+pub fn resolution(filename: &str) -> Option<u32> {
+    let pattern = Regex::new(concat!(
+        r"(?i)(?:^|[^0-9a-z])",
+        r"(?:(2160|1440|1080|720|576|480)[pi]|4k|uhd)",
+        r"(?:[^0-9a-z]|$)"
+    ))
+    .unwrap();
+    let caps = pattern.captures(filename)?;
+
+    match caps.get(1) {
+        Some(m) => m.as_str().parse::<u32>().ok(),
+        None => Some(2160),
+    }
 }
 
 const CERTAINTY_PERFECT: f64 = 0.9;
 const CERTAINTY_GOOD: f64 = 0.7;
 const CERTAINTY_UNSURE: f64 = 0.2;
 
+/// Map an absolute episode number onto a season and episode, given the number of episodes in
+/// each season, in order
+pub fn absolute_to_seasonal(season_counts: &[(u32, u32)], absolute: u32) -> Option<(u32, u32)> {
+    if absolute == 0 {
+        return None;
+    }
+
+    let mut remaining = absolute;
+    for &(season, count) in season_counts {
+        if remaining <= count {
+            return Some((season, remaining));
+        }
+        remaining -= count;
+    }
+
+    None
+}
+
 impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let desc = match self {
@@ -39,58 +189,76 @@ impl fmt::Display for ParseError {
             ParseError::BadFilename => "could not calculate season / episode number from filename",
             ParseError::BadPath => "could not find TV show name",
             ParseError::BadExtension => "file extension is not permitted",
+            ParseError::BadYear => "could not find the film's release year",
         };
 
         write!(f, "{}", desc)
     }
 }
 
-impl fmt::Display for Episode {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let red = Style::new().red();
-        let red_bold = Style::new().red().bold();
-        let yellow = Style::new().yellow();
-        let cyan = Style::new().cyan();
-        let green = Style::new().green();
-
-        let pretty_confidence = {
-            let formatted = format!("{:.0}%", self.show_certainty * 100.0);
-            if self.show_certainty > CERTAINTY_PERFECT {
-                green.apply_to(formatted)
-            } else if self.show_certainty > CERTAINTY_GOOD {
-                yellow.apply_to(formatted)
-            } else if self.show_certainty > CERTAINTY_UNSURE {
-                red.apply_to(formatted)
-            } else {
-                red_bold.apply_to(formatted)
-            }
-        };
+/// Describe an upload for the user: the local file, where it's going, and how sure we are that
+/// we've identified it correctly
+pub(crate) fn describe_upload(local_path: &Path, remote: &str, certainty: f64) -> String {
+    let red = Style::new().red();
+    let red_bold = Style::new().red().bold();
+    let yellow = Style::new().yellow();
+    let cyan = Style::new().cyan();
+    let green = Style::new().green();
 
-        let local_trunc = {
-            let full = self.local_path.to_string_lossy();
-            let len = full.len();
+    let pretty_confidence = {
+        let formatted = format!("{:.0}%", certainty * 100.0);
+        if certainty > CERTAINTY_PERFECT {
+            green.apply_to(formatted)
+        } else if certainty > CERTAINTY_GOOD {
+            yellow.apply_to(formatted)
+        } else if certainty > CERTAINTY_UNSURE {
+            red.apply_to(formatted)
+        } else {
+            red_bold.apply_to(formatted)
+        }
+    };
 
-            if len <= 40 {
-                full.to_string()
-            } else {
-                format!("…{}", &full[len - 39..])
-            }
-        };
+    let local_trunc = {
+        let full = local_path.to_string_lossy();
+        let len = full.len();
 
-        let pretty_remote = format!(
-            "{}: S{:02} E{:02}",
-            self.show_name, self.season_num, self.episode_num
-        );
+        if len <= 40 {
+            full.to_string()
+        } else {
+            format!("…{}", &full[len - 39..])
+        }
+    };
 
-        let desc = format!(
-            "{:>40} {} {} (confidence: {})",
-            local_trunc,
-            cyan.apply_to("--->"),
-            pretty_remote,
-            pretty_confidence
-        );
+    format!(
+        "{:>40} {} {} (confidence: {})",
+        local_trunc,
+        cyan.apply_to("--->"),
+        remote,
+        pretty_confidence
+    )
+}
 
-        write!(f, "{}", desc)
+impl fmt::Display for Episode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let pretty_remote = if self.is_subtitle() {
+            format!(
+                "{}: {} subtitles [{}]",
+                self.show_name,
+                self.numbering(),
+                self.tags.join(", ")
+            )
+        } else {
+            match &self.title {
+                Some(title) => format!("{}: {} - {}", self.show_name, self.numbering(), title),
+                None => format!("{}: {}", self.show_name, self.numbering()),
+            }
+        };
+
+        write!(
+            f,
+            "{}",
+            describe_upload(&self.local_path, &pretty_remote, self.show_certainty)
+        )
     }
 }
 
@@ -99,7 +267,10 @@ impl PartialEq for Episode {
         self.show_name == other.show_name
             && self.season_num == other.season_num
             && self.episode_num == other.episode_num
+            && self.last_episode_num == other.last_episode_num
+            && self.air_date == other.air_date
             && self.ext == other.ext
+            && self.tags == other.tags
     }
 }
 impl Eq for Episode {}
@@ -115,7 +286,16 @@ impl Ord for Episode {
         if self.episode_num != other.episode_num {
             return self.episode_num.cmp(&other.episode_num);
         }
-        self.ext.cmp(&other.ext)
+        if self.last_episode_num != other.last_episode_num {
+            return self.last_episode_num.cmp(&other.last_episode_num);
+        }
+        if self.air_date != other.air_date {
+            return self.air_date.cmp(&other.air_date);
+        }
+        if self.ext != other.ext {
+            return self.ext.cmp(&other.ext);
+        }
+        self.tags.cmp(&other.tags)
     }
 }
 impl PartialOrd for Episode {
@@ -126,31 +306,143 @@ impl PartialOrd for Episode {
 
 impl Episode {
     // Parse filename into season, episode, and extension; e.g. S01 E01.mkv -> (1, 1, mkv)
-    fn parse_filename(filename: &str) -> Option<(u32, u32, String)> {
-        let parse = |pattern: Regex| {
+    fn parse_filename(filename: &str) -> Option<(EpisodeNumber, String)> {
+        let parse = |pattern: &Regex| {
+            let caps = pattern.captures(filename)?;
+            let season = caps.name("season")?.as_str().parse::<u32>().unwrap();
+            let episode = caps.name("episode")?.as_str().parse::<u32>().unwrap();
+            let last = caps
+                .name("last")
+                .filter(|m| {
+                    // A bare number running into letters, like "-720p", is a resolution rather
+                    // than the end of a range
+                    filename[..m.start()].ends_with(['E', 'e'])
+                        || !filename[m.end()..].starts_with(|c: char| c.is_ascii_alphanumeric())
+                })
+                .map(|m| m.as_str().parse::<u32>().unwrap())
+                .filter(|&last| last > episode);
+            let ext = caps.name("ext")?.as_str().to_string();
+
+            Some((EpisodeNumber::Seasonal(season, episode, last), ext))
+        };
+
+        // Anime releases are typically "[Group] Show - 137 [1080p].mkv", numbered from the
+        // first episode of the show; this needs to take precedence over the looser 3-digit
+        // pattern below, which would read it as S01 E37
+        let parse_absolute = |pattern: &Regex| {
+            let caps = pattern.captures(filename)?;
+            let episode = caps.get(1)?.as_str().parse::<u32>().unwrap();
+            let ext = caps.get(2)?.as_str().to_string();
+
+            Some((EpisodeNumber::Absolute(episode), ext))
+        };
+
+        // Daily shows are named by air date, e.g. Show.2024.03.14.Guest.Name.mkv; these need to be
+        // tried before the 1x02 pattern, which would read that as S03 E14
+        let parse_dated = |pattern: &Regex| {
             let caps = pattern.captures(filename)?;
-            let season = caps.get(1)?.as_str().parse::<u32>().unwrap();
-            let episode = caps.get(2)?.as_str().parse::<u32>().unwrap();
-            let ext = caps.get(3)?.as_str().to_string();
+            let date = NaiveDate::from_ymd_opt(
+                caps.name("year")?.as_str().parse::<i32>().unwrap(),
+                caps.name("month")?.as_str().parse::<u32>().unwrap(),
+                caps.name("day")?.as_str().parse::<u32>().unwrap(),
+            )?;
+            let ext = caps.name("ext")?.as_str().to_string();
 
-            Some((season, episode, ext))
+            Some((EpisodeNumber::Dated(date), ext))
         };
 
-        // Patterns taken from smart-rename, minus the anime one
-        for pattern in vec![
-            Regex::new(r"^.*[Ss]([0-9]{2})[\s\-\.]*[Ee]([0-9]{2}).*\.([a-z0-9]+)$").unwrap(),
-            Regex::new(r"^.*[^0-9]([0-9]{1,2})[x\.]([0-9]{1,2}).*\.([a-z0-9]+)$").unwrap(),
-            Regex::new(r"^.*[\s\-\.]([1-9])([0-9]{2}).*\.([a-z0-9]+)$").unwrap(),
-        ] {
-            let result = parse(pattern);
-            if result.is_some() {
-                return result;
+        // Patterns taken from smart-rename. The first also accepts multi-episode files in the
+        // forms E05E06, E05-E06 and E05-06, capturing the last episode in the file
+        let seasonal = [
+            Regex::new(concat!(
+                r"^.*[Ss](?P<season>[0-9]{2})[\s\-\.]*[Ee](?P<episode>[0-9]{2,4})",
+                r"(?:(?:-?[Ee]|-)(?P<last>[0-9]{2,4}))*",
+                r"(?:[^0-9].*)?\.(?P<ext>[a-z0-9]+)$"
+            ))
+            .unwrap(),
+            Regex::new(
+                r"^.*[^0-9](?P<season>[0-9]{1,2})[x\.](?P<episode>[0-9]{1,2}).*\.(?P<ext>[a-z0-9]+)$",
+            )
+            .unwrap(),
+        ];
+        let dated = [
+            Regex::new(concat!(
+                r"^(?:.*[^0-9])?(?P<year>(?:19|20)[0-9]{2})[\s\-\._](?P<month>[0-9]{2})",
+                r"[\s\-\._](?P<day>[0-9]{2})(?:[^0-9].*)?\.(?P<ext>[a-z0-9]+)$"
+            ))
+            .unwrap(),
+            Regex::new(concat!(
+                r"^(?:.*[^0-9])?(?P<day>[0-9]{2})[\s\-\._](?P<month>[0-9]{2})",
+                r"[\s\-\._](?P<year>(?:19|20)[0-9]{2})(?:[^0-9].*)?\.(?P<ext>[a-z0-9]+)$"
+            ))
+            .unwrap(),
+        ];
+        let absolute =
+            Regex::new(r"^\[[^\]]*\].*\s-\s([0-9]{1,4})(?:v[0-9])?(?:[\s\[\(].*)?\.([a-z0-9]+)$")
+                .unwrap();
+        let seasonal_loose =
+            Regex::new(r"^.*[\s\-\.](?P<season>[1-9])(?P<episode>[0-9]{2}).*\.(?P<ext>[a-z0-9]+)$")
+                .unwrap();
+
+        parse(&seasonal[0])
+            .or_else(|| dated.iter().find_map(parse_dated))
+            .or_else(|| parse(&seasonal[1]))
+            .or_else(|| parse_absolute(&absolute))
+            .or_else(|| parse(&seasonal_loose))
+    }
+
+    // Parse an episode number and extension from a filename without a season number, like
+    // "E05.mkv", "Episode 5.mkv" or "05 - Title.mkv"
+    fn parse_episode_only(filename: &str) -> Option<(u32, String)> {
+        let patterns = [
+            Regex::new(concat!(
+                r"^(?:.*[^A-Za-z])?(?i:e|ep|episode)[\s\._]*([0-9]{1,3})",
+                r"(?:[^0-9].*)?\.([a-z0-9]+)$"
+            ))
+            .unwrap(),
+            Regex::new(r"^([0-9]{1,3})(?:[\s\._\-].*)?\.([a-z0-9]+)$").unwrap(),
+        ];
+
+        patterns.iter().find_map(|pattern| {
+            let caps = pattern.captures(filename)?;
+            let episode = caps.get(1)?.as_str().parse::<u32>().unwrap();
+            let ext = caps.get(2)?.as_str().to_string();
+            Some((episode, ext))
+        })
+    }
+
+    /// Parse an episode from its filename, as with `Episode::from`, but using the given season
+    /// (usually from the folder it's in) if the filename only has an episode number
+    pub fn from_in_season<T: AsRef<str>>(
+        path: &Path,
+        filename: &str,
+        season: u32,
+        show_name: &str,
+        show_certainty: f64,
+        allowed_exts: &[T],
+    ) -> Result<Episode, ParseError> {
+        match Self::from(path, filename, show_name, show_certainty, allowed_exts) {
+            Err(ParseError::BadFilename) => {
+                let (episode, ext) =
+                    Self::parse_episode_only(filename).ok_or(ParseError::BadFilename)?;
+                let number = EpisodeNumber::Seasonal(season, episode, None);
+                Self::build(
+                    path,
+                    filename,
+                    number,
+                    ext,
+                    show_name,
+                    show_certainty,
+                    allowed_exts,
+                )
             }
+            res => res,
         }
-
-        None
     }
 
+    /// Parse an episode from its filename. Absolutely-numbered episodes are provisionally filed
+    /// under season 1, pending a proper mapping to a season, and episodes named by air date have
+    /// no season or episode number until one is found.
     pub fn from<T: AsRef<str>>(
         path: &Path,
         filename: &str,
@@ -158,37 +450,167 @@ impl Episode {
         show_certainty: f64,
         allowed_exts: &[T],
     ) -> Result<Episode, ParseError> {
-        let exts: Vec<String> = allowed_exts
-            .iter()
-            .map(|s| s.as_ref().to_string())
-            .collect();
-
-        let (season_num, episode_num, ext) =
-            Self::parse_filename(filename).ok_or(ParseError::BadFilename)?;
+        let (number, ext) = Self::parse_filename(filename).ok_or(ParseError::BadFilename)?;
+        Self::build(
+            path,
+            filename,
+            number,
+            ext,
+            show_name,
+            show_certainty,
+            allowed_exts,
+        )
+    }
 
-        if !exts.contains(&ext) {
+    fn build<T: AsRef<str>>(
+        path: &Path,
+        filename: &str,
+        number: EpisodeNumber,
+        ext: String,
+        show_name: &str,
+        show_certainty: f64,
+        allowed_exts: &[T],
+    ) -> Result<Episode, ParseError> {
+        if !allowed_exts.iter().any(|e| e.as_ref() == ext) {
             return Err(ParseError::BadExtension);
         }
 
+        let (season_num, episode_num, last_episode_num, absolute_num, air_date) = match number {
+            EpisodeNumber::Seasonal(season, episode, last) => (season, episode, last, None, None),
+            EpisodeNumber::Absolute(episode) => (1, episode, None, Some(episode), None),
+            EpisodeNumber::Dated(date) => (0, 0, None, None, Some(date)),
+        };
+
         Ok(Episode {
             local_path: path.to_path_buf(),
             show_name: show_name.to_string(),
-            show_certainty: show_certainty,
-            season_num: season_num,
-            episode_num: episode_num,
-            ext: ext,
+            show_certainty,
+            season_num,
+            episode_num,
+            last_episode_num,
+            absolute_num,
+            air_date,
+            title: None,
+            replaces: None,
+            tags: if SUBTITLE_EXTS.contains(&ext.as_str()) {
+                subtitle_tags(filename)
+            } else {
+                vec![]
+            },
+            ext,
+            subtitles: vec![],
+        })
+    }
+
+    /// The last episode contained in this file; the same as `episode_num` unless the file holds
+    /// several episodes
+    pub fn last_episode(&self) -> u32 {
+        self.last_episode_num.unwrap_or(self.episode_num)
+    }
+
+    pub fn is_subtitle(&self) -> bool {
+        SUBTITLE_EXTS.contains(&self.ext.as_str())
+    }
+
+    /// Check whether this is a subtitle file for the given video, i.e. it's for the same episode
+    pub fn is_subtitle_for(&self, video: &Episode) -> bool {
+        self.is_subtitle()
+            && !video.is_subtitle()
+            && self.show_name == video.show_name
+            && self.numbering() == video.numbering()
+    }
+
+    // Season and episode(s) in the form Kodi expects, e.g. S02 E05 or S02 E05-E06, or the air
+    // date for episodes we only know the date of
+    fn numbering(&self) -> String {
+        if let Some(date) = self.air_date {
+            return date.format("%Y-%m-%d").to_string();
+        }
+
+        match self.last_episode_num {
+            Some(last) => format!(
+                "S{:02} E{:02}-E{:02}",
+                self.season_num, self.episode_num, last
+            ),
+            None => format!("S{:02} E{:02}", self.season_num, self.episode_num),
+        }
+    }
+
+    /// Check whether a remote file already holds any of the episodes in this file in the same
+    /// form, given its path within the show's folder. Ranges are compared, so a remote S02 E05-E06
+    /// covers both E05 and E06; files in season folders which only have an episode number take the
+    /// folder's season.
+    pub fn covered_by(&self, remote_path: &str) -> bool {
+        self.remote_ext(remote_path) == Some(self.ext.clone())
+    }
+
+    /// Check whether a remote file holds any of the episodes in this file in another form, e.g.
+    /// an .mkv where we have an .mp4
+    pub fn variant_of(&self, remote_path: &str) -> bool {
+        self.remote_ext(remote_path)
+            .is_some_and(|ext| ext != self.ext)
+    }
+
+    /// Check whether a remote file holds exactly the episodes in this file, no more and no fewer,
+    /// so that replacing it with this one loses nothing
+    pub fn same_episodes_as(&self, remote_path: &str) -> bool {
+        match Self::parse_remote(remote_path) {
+            Some((EpisodeNumber::Seasonal(season, first, last), _)) if self.air_date.is_none() => {
+                season == self.season_num
+                    && first == self.episode_num
+                    && last.unwrap_or(first) == self.last_episode()
+            }
+            Some((EpisodeNumber::Dated(date), _)) => self.air_date == Some(date),
+            _ => false,
+        }
+    }
+
+    // Parse the path of a remote file within its show's folder, using the season folder it's in
+    // if its name has no season
+    fn parse_remote(remote_path: &str) -> Option<(EpisodeNumber, String)> {
+        let mut segments = remote_path.rsplit('/');
+        let remote_filename = segments.next().unwrap_or(remote_path);
+        let season_hint = segments.next().and_then(season_from_folder);
+
+        Self::parse_filename(remote_filename).or_else(|| {
+            let (episode, ext) = Self::parse_episode_only(remote_filename)?;
+            Some((EpisodeNumber::Seasonal(season_hint?, episode, None), ext))
         })
     }
 
+    // If a remote file holds any of the episodes in this file, find its extension. Videos only
+    // match videos, and subtitles only match subtitles with the same tags.
+    fn remote_ext(&self, remote_path: &str) -> Option<String> {
+        let remote_filename = remote_path.rsplit('/').next().unwrap_or(remote_path);
+        let (number, ext) = Self::parse_remote(remote_path)?;
+
+        // Only subtitles have tags; words in a video's title could be mistaken for them
+        let remote_subtitle = SUBTITLE_EXTS.contains(&ext.as_str());
+        let same_kind = remote_subtitle == self.is_subtitle()
+            && (!remote_subtitle || subtitle_tags(remote_filename) == self.tags);
+
+        let same_episode = match number {
+            EpisodeNumber::Seasonal(season, first, last) if self.air_date.is_none() => {
+                let last = last.unwrap_or(first);
+
+                season == self.season_num
+                    && first <= self.last_episode()
+                    && self.episode_num <= last
+            }
+            EpisodeNumber::Dated(date) => self.air_date == Some(date),
+            _ => false,
+        };
+
+        (same_kind && same_episode).then_some(ext)
+    }
+
+    /// Remote filename, including any subtitle tags, e.g. S01 E01.en.forced.srt
     pub fn remote_filename(&self) -> String {
-        format!(
-            "S{:02} E{:02}.{}",
-            self.season_num, self.episode_num, self.ext
-        )
+        let tags: String = self.tags.iter().map(|t| format!(".{}", t)).collect();
+        format!("{}{}.{}", self.numbering(), tags, self.ext)
     }
-    pub fn remote_subpath(&self) -> Utf8UnixPathBuf {
-        let mut p = Utf8UnixPathBuf::from(&self.show_name);
-        p.push(self.remote_filename());
-        p
+    /// Path of this episode within the remote TV directory, laid out by the given template
+    pub fn remote_subpath(&self, template: &NamingTemplate) -> Utf8UnixPathBuf {
+        template.render(self)
     }
 }
@@ -0,0 +1,208 @@
+#[cfg(test)]
+mod tests;
+
+use std::fmt;
+use std::path::Path;
+
+use serde::Deserialize;
+use thiserror::Error;
+use typed_path::Utf8UnixPathBuf;
+
+use super::Episode;
+
+/// The layout Kodi expects, and what we've always used: `<show>/S01 E01.mkv`, or
+/// `<show>/S01 E01 - The Title.mkv` when episode titles are looked up
+pub const DEFAULT_TEMPLATE: &str = "{show}/S{season:02} E{episode:02}{title}.{ext}";
+
+#[derive(Error, Debug, PartialEq)]
+pub enum TemplateError {
+    #[error("unknown field {{{0}}} in naming template")]
+    UnknownField(String),
+    #[error(
+        "bad field {{{0}}} in naming template; use a name and optional width, like {{season:02}}"
+    )]
+    BadField(String),
+    #[error("unclosed {{ in naming template")]
+    Unclosed,
+    #[error("naming template must start with \"{{show}}/\", so each show has its own folder")]
+    NoShowFolder,
+    #[error(
+        "naming template gives names like \"{0}\", which can't be read back to find episodes \
+         already uploaded; use zero-padded {{season:02}} and {{episode:02}}"
+    )]
+    Unreadable(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Field {
+    Show,
+    Season,
+    Episode,
+    Title,
+    Ext,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Part {
+    Literal(String),
+    // A field to substitute, zero-padded to the given width if numeric
+    Field(Field, usize),
+}
+
+/// A template for the path of an episode within the remote TV directory, like
+/// `{show}/Season {season:02}/{show} - S{season:02}E{episode:02}.{ext}`.
+///
+/// Multi-episode files render `{episode}` as a range, e.g. `05-E06`, and `{ext}` includes any
+/// subtitle tags, e.g. `en.forced.srt`. `{title}` renders as ` - The Title` if the episode's title
+/// is known, and nothing otherwise. Episodes we only know the air date of are always named
+/// `<show>/<date>.<ext>`, as they have no season or episode number to fill in.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub struct NamingTemplate {
+    source: String,
+    parts: Vec<Part>,
+}
+
+impl NamingTemplate {
+    pub fn new(template: &str) -> Result<NamingTemplate, TemplateError> {
+        if !template.starts_with("{show}/") {
+            return Err(TemplateError::NoShowFolder);
+        }
+
+        let mut parts = vec![];
+        let mut rest = template;
+
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(Part::Literal(rest[..start].to_string()));
+            }
+            let end = rest[start..].find('}').ok_or(TemplateError::Unclosed)? + start;
+            parts.push(Self::parse_field(&rest[start + 1..end])?);
+            rest = &rest[end + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Literal(rest.to_string()));
+        }
+
+        let naming = NamingTemplate {
+            source: template.to_string(),
+            parts,
+        };
+        naming.check_readable()?;
+
+        Ok(naming)
+    }
+
+    // Episodes already on the remote are found by parsing their names, so anything this template
+    // renders has to be read back as the same episode, or it'd be uploaded again on every sync
+    fn check_readable(&self) -> Result<(), TemplateError> {
+        let mut sample =
+            Episode::from(Path::new("S02E05.mkv"), "S02E05.mkv", "Show", 1.0, &["mkv"])
+                .expect("sample episode should parse");
+        sample.title = Some("Title".to_string());
+
+        let rendered = self.render(&sample);
+        let readable = rendered
+            .strip_prefix(&sample.show_name)
+            .is_ok_and(|relative| sample.covered_by(relative.as_str()));
+
+        match readable {
+            true => Ok(()),
+            false => Err(TemplateError::Unreadable(rendered.to_string())),
+        }
+    }
+
+    // Parse the inside of a {field:width} placeholder
+    fn parse_field(spec: &str) -> Result<Part, TemplateError> {
+        let (name, width) = match spec.split_once(':') {
+            Some((name, width)) => {
+                let width = width
+                    .parse::<usize>()
+                    .map_err(|_| TemplateError::BadField(spec.to_string()))?;
+                (name, width)
+            }
+            None => (spec, 0),
+        };
+
+        let field = match name {
+            "show" => Field::Show,
+            "season" => Field::Season,
+            "episode" => Field::Episode,
+            "title" => Field::Title,
+            "ext" => Field::Ext,
+            _ => return Err(TemplateError::UnknownField(name.to_string())),
+        };
+
+        Ok(Part::Field(field, width))
+    }
+
+    fn render_field(ep: &Episode, field: Field, width: usize) -> String {
+        match field {
+            Field::Show => ep.show_name.clone(),
+            Field::Season => format!("{:0w$}", ep.season_num, w = width),
+            Field::Episode => match ep.last_episode_num {
+                Some(last) => format!("{:0w$}-E{:0w$}", ep.episode_num, last, w = width),
+                None => format!("{:0w$}", ep.episode_num, w = width),
+            },
+            Field::Title => ep
+                .title
+                .as_ref()
+                .map(|t| format!(" - {}", t))
+                .unwrap_or_default(),
+            Field::Ext => ep
+                .tags
+                .iter()
+                .chain(std::iter::once(&ep.ext))
+                .cloned()
+                .collect::<Vec<String>>()
+                .join("."),
+        }
+    }
+
+    /// Whether episode titles appear anywhere in the rendered path
+    pub fn has_title(&self) -> bool {
+        self.parts
+            .iter()
+            .any(|part| matches!(part, Part::Field(Field::Title, _)))
+    }
+
+    /// Render the path of an episode, relative to the remote TV directory
+    pub fn render(&self, ep: &Episode) -> Utf8UnixPathBuf {
+        if ep.air_date.is_some() {
+            let mut p = Utf8UnixPathBuf::from(&ep.show_name);
+            p.push(ep.remote_filename());
+            return p;
+        }
+
+        let rendered: String = self
+            .parts
+            .iter()
+            .map(|part| match part {
+                Part::Literal(s) => s.clone(),
+                Part::Field(field, width) => Self::render_field(ep, *field, *width),
+            })
+            .collect();
+
+        Utf8UnixPathBuf::from(rendered)
+    }
+}
+
+impl Default for NamingTemplate {
+    fn default() -> Self {
+        Self::new(DEFAULT_TEMPLATE).unwrap()
+    }
+}
+
+impl TryFrom<String> for NamingTemplate {
+    type Error = TemplateError;
+
+    fn try_from(template: String) -> Result<Self, Self::Error> {
+        Self::new(&template)
+    }
+}
+
+impl fmt::Display for NamingTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
@@ -0,0 +1,124 @@
+use super::*;
+
+use crate::tests as utils;
+
+fn episode(filename: &str) -> Episode {
+    let path = utils::test_path("irrelevant.mkv");
+    let exts = vec!["mkv".to_string(), "srt".to_string()];
+    Episode::from(&path, filename, "All My Circuits", 1.0, &exts).unwrap()
+}
+
+#[test]
+fn default_template_matches_kodi_layout() {
+    let template = NamingTemplate::default();
+
+    for f in [
+        "S01E02.mkv",
+        "S02E05E06.mkv",
+        "S01E02.en.forced.srt",
+        "2024.03.14.mkv",
+    ] {
+        let ep = episode(f);
+        let mut expected = Utf8UnixPathBuf::from("All My Circuits");
+        expected.push(ep.remote_filename());
+
+        assert_eq!(template.render(&ep), expected);
+    }
+}
+
+#[test]
+fn season_folder_template() {
+    let template =
+        NamingTemplate::new("{show}/Season {season:02}/{show} - S{season:02}E{episode:02}.{ext}")
+            .unwrap();
+
+    assert_eq!(
+        template.render(&episode("S01E02.mkv")),
+        "All My Circuits/Season 01/All My Circuits - S01E02.mkv"
+    );
+    assert_eq!(
+        template.render(&episode("S02E05-06.mkv")),
+        "All My Circuits/Season 02/All My Circuits - S02E05-E06.mkv"
+    );
+    assert_eq!(
+        template.render(&episode("S01E02.en.srt")),
+        "All My Circuits/Season 01/All My Circuits - S01E02.en.srt"
+    );
+    assert_eq!(
+        NamingTemplate::new("{show}/Season {season}/E{episode}.{ext}")
+            .unwrap()
+            .render(&episode("S01E02.mkv")),
+        "All My Circuits/Season 1/E2.mkv"
+    );
+}
+
+#[test]
+fn covered_by_template_layout() {
+    let ep = episode("S01E02.mkv");
+
+    assert!(ep.covered_by("Season 01/All My Circuits - S01E02.mkv"));
+    assert!(ep.covered_by("Season 1/E2.mkv"));
+    assert!(!ep.covered_by("Season 2/E2.mkv"));
+    assert!(!ep.covered_by("E2.mkv"));
+}
+
+#[test]
+fn invalid_templates() {
+    for (template, expected) in [
+        (
+            "S{season:02} E{episode:02}.{ext}",
+            TemplateError::NoShowFolder,
+        ),
+        (
+            "{show}/{quality}.{ext}",
+            TemplateError::UnknownField("quality".to_string()),
+        ),
+        (
+            "{show}/S{season:xx}.{ext}",
+            TemplateError::BadField("season:xx".to_string()),
+        ),
+        ("{show}/S{season", TemplateError::Unclosed),
+    ] {
+        assert_eq!(NamingTemplate::new(template).unwrap_err(), expected);
+    }
+}
+
+#[test]
+fn unreadable_templates() {
+    for (template, rendered) in [
+        (
+            "{show}/{show} S{season}E{episode}.{ext}",
+            "Show/Show S2E5.mkv",
+        ),
+        ("{show}/E{episode:02}{title}.{ext}", "Show/E05 - Title.mkv"),
+        (
+            "{show}/Season {season:02}/{title}.{ext}",
+            "Show/Season 02/ - Title.mkv",
+        ),
+        ("{show}/S{season:02}E{episode:02}.txt", "Show/S02E05.txt"),
+    ] {
+        let expected = TemplateError::Unreadable(rendered.to_string());
+        assert_eq!(
+            NamingTemplate::new(template).unwrap_err(),
+            expected,
+            "{}",
+            template
+        );
+    }
+}
+
+#[test]
+fn title_in_filename() {
+    let template = NamingTemplate::default();
+    let mut ep = episode("S01E02.mkv");
+    ep.title = Some("The Title".to_string());
+
+    assert_eq!(
+        template.render(&ep),
+        "All My Circuits/S01 E02 - The Title.mkv"
+    );
+    assert!(ep.covered_by("S01 E02 - The Title.mkv"));
+
+    ep.title = None;
+    assert_eq!(template.render(&ep), "All My Circuits/S01 E02.mkv");
+}
@@ -3,7 +3,10 @@ use super::*;
 use crate::tests as utils;
 
 fn allowed_exts() -> Vec<String> {
-    vec!["mkv", "mp4"].into_iter().map(String::from).collect()
+    vec!["mkv", "mp4", "srt"]
+        .into_iter()
+        .map(String::from)
+        .collect()
 }
 
 #[test]
@@ -16,7 +19,14 @@ fn valid_episode_exact() {
         show_certainty: 1.0,
         season_num: 1,
         episode_num: 2,
+        last_episode_num: None,
+        absolute_num: None,
+        air_date: None,
+        title: None,
+        replaces: None,
         ext: String::from("mkv"),
+        tags: vec![],
+        subtitles: vec![],
     };
 
     let actual = Episode::from(
@@ -46,7 +56,14 @@ fn valid_episode_fuzzy() {
             show_certainty: 1.0,
             season_num: 1,
             episode_num: 2,
+            last_episode_num: None,
+            absolute_num: None,
+            air_date: None,
+            title: None,
+            replaces: None,
             ext: String::from("mkv"),
+            tags: vec![],
+            subtitles: vec![],
         };
 
         let actual = Episode::from(&path, f, "All My Circuits", 1.0, &allowed_exts()).unwrap();
@@ -54,3 +71,199 @@ fn valid_episode_fuzzy() {
         assert_eq!(actual, expected);
     }
 }
+
+#[test]
+fn valid_episode_absolute() {
+    let path = utils::test_path("irrelevant.mkv");
+
+    for (f, expected) in [
+        ("[Morbotron] All My Circuits - 137 [1080p].mkv", 137),
+        (
+            "[Morbotron] All My Circuits - 05v2 (BD 720p) [ABCD1234].mkv",
+            5,
+        ),
+        ("[Morbotron] All My Circuits - 1002.mkv", 1002),
+    ] {
+        let actual = Episode::from(&path, f, "All My Circuits", 1.0, &allowed_exts()).unwrap();
+
+        assert_eq!(actual.absolute_num, Some(expected));
+        assert_eq!(actual.season_num, 1);
+        assert_eq!(actual.episode_num, expected);
+
+        // Unmapped episodes must be found again once uploaded, however long the number
+        assert!(actual.covered_by(&actual.remote_filename()), "{}", f);
+    }
+}
+
+#[test]
+fn absolute_to_seasonal_mapping() {
+    let seasons = [(1, 13), (2, 26), (3, 12)];
+
+    assert_eq!(absolute_to_seasonal(&seasons, 1), Some((1, 1)));
+    assert_eq!(absolute_to_seasonal(&seasons, 13), Some((1, 13)));
+    assert_eq!(absolute_to_seasonal(&seasons, 14), Some((2, 1)));
+    assert_eq!(absolute_to_seasonal(&seasons, 51), Some((3, 12)));
+    assert_eq!(absolute_to_seasonal(&seasons, 52), None);
+    assert_eq!(absolute_to_seasonal(&seasons, 0), None);
+}
+
+#[test]
+fn valid_episode_multi() {
+    let path = utils::test_path("irrelevant.mkv");
+
+    for f in [
+        "All.My.Circuits.S02E05E06.1080p.mkv",
+        "All My Circuits - S02E05-E06.mkv",
+        "all.my.circuits.s02e05-06.mkv",
+        "S02 E05-E06.mkv",
+    ] {
+        let actual = Episode::from(&path, f, "All My Circuits", 1.0, &allowed_exts()).unwrap();
+
+        assert_eq!(actual.season_num, 2);
+        assert_eq!(actual.episode_num, 5);
+        assert_eq!(actual.last_episode_num, Some(6));
+        assert_eq!(actual.remote_filename(), "S02 E05-E06.mkv");
+    }
+
+    for f in [
+        "All.My.Circuits.S02E05-1080p.mkv",
+        "All.My.Circuits.S02E05-720p.mkv",
+        "All.My.Circuits.S02E05-480p.mkv",
+    ] {
+        let single = Episode::from(&path, f, "All My Circuits", 1.0, &allowed_exts()).unwrap();
+        assert_eq!(single.last_episode_num, None, "{}", f);
+        assert_eq!(single.remote_filename(), "S02 E05.mkv");
+    }
+}
+
+#[test]
+fn covered_by_overlapping_range() {
+    let path = utils::test_path("irrelevant.mkv");
+    let ep = |f: &str| Episode::from(&path, f, "All My Circuits", 1.0, &allowed_exts()).unwrap();
+
+    let multi = ep("S02E05E06.mkv");
+    assert!(multi.covered_by("S02 E05-E06.mkv"));
+    assert!(multi.covered_by("S02 E06.mkv"));
+    assert!(!multi.covered_by("S02 E07.mkv"));
+    assert!(!multi.covered_by("S01 E05-E06.mkv"));
+    assert!(!multi.covered_by("S02 E05-E06.mp4"));
+
+    let single = ep("S02E06.mkv");
+    assert!(single.covered_by("S02 E05-E06.mkv"));
+    assert!(!single.covered_by("S02 E04-E05.mkv"));
+}
+
+#[test]
+fn valid_episode_dated() {
+    let path = utils::test_path("irrelevant.mkv");
+    let date = NaiveDate::from_ymd_opt(2024, 3, 14).unwrap();
+
+    for f in [
+        "Late.Night.Calculon.2024.03.14.Guest.Name.1080p.mkv",
+        "Late Night Calculon - 2024-03-14.mkv",
+        "late_night_calculon_14.03.2024.mkv",
+        "2024-03-14.mkv",
+    ] {
+        let actual = Episode::from(&path, f, "Late Night Calculon", 1.0, &allowed_exts()).unwrap();
+
+        assert_eq!(actual.air_date, Some(date));
+        assert_eq!(actual.remote_filename(), "2024-03-14.mkv");
+        assert!(actual.covered_by("2024-03-14.mkv"));
+        assert!(!actual.covered_by("2024-03-15.mkv"));
+    }
+
+    assert_eq!(
+        Episode::from(
+            &path,
+            "Late.Night.Calculon.2024.13.14.mkv",
+            "Late Night Calculon",
+            1.0,
+            &allowed_exts()
+        )
+        .unwrap()
+        .air_date,
+        None
+    );
+}
+
+#[test]
+fn subtitle_tags_detected() {
+    for (f, expected) in [
+        ("S01 E01.en.srt", vec!["en"]),
+        ("S01 E01.forced.srt", vec!["forced"]),
+        (
+            "All.My.Circuits.S01E01.English.Forced.srt",
+            vec!["en", "forced"],
+        ),
+        ("All.My.Circuits.S01E01.eng.SDH.srt", vec!["en", "sdh"]),
+        ("All My Circuits - S01E01 [fr] (HI).srt", vec!["fr", "sdh"]),
+        ("All.My.Circuits.S01E01.1080p.srt", vec![]),
+    ] {
+        assert_eq!(subtitle_tags(f), expected, "{}", f);
+    }
+}
+
+#[test]
+fn subtitle_remote_filename() {
+    let path = utils::test_path("irrelevant.srt");
+    let ep = |f: &str| Episode::from(&path, f, "All My Circuits", 1.0, &allowed_exts()).unwrap();
+
+    let video = ep("All.My.Circuits.S01E01.1080p.mkv");
+    let english = ep("All.My.Circuits.S01E01.en.srt");
+    let forced = ep("All.My.Circuits.S01E01.en.forced.srt");
+
+    assert_eq!(english.remote_filename(), "S01 E01.en.srt");
+    assert_eq!(forced.remote_filename(), "S01 E01.en.forced.srt");
+    assert_ne!(english, forced);
+
+    assert!(english.is_subtitle_for(&video));
+    assert!(!video.is_subtitle_for(&english));
+    assert!(!ep("All.My.Circuits.S01E02.en.srt").is_subtitle_for(&video));
+
+    assert!(english.covered_by("S01 E01.en.srt"));
+    assert!(!english.covered_by("S01 E01.en.forced.srt"));
+    assert!(!video.covered_by("S01 E01.en.srt"));
+
+    // Words in an episode title aren't subtitle tags
+    let titled = ep("All.My.Circuits.S01E02.1080p.mkv");
+    assert!(titled.covered_by("S01 E02 - Say It.mkv"));
+    assert!(!titled.variant_of("S01 E02 - Say It.mkv"));
+    assert!(titled.variant_of("S01 E02 - Say It.mp4"));
+}
+
+#[test]
+fn season_folder_hint() {
+    assert_eq!(season_from_folder("Season 2"), Some(2));
+    assert_eq!(season_from_folder("season.02"), Some(2));
+    assert_eq!(season_from_folder("Series 10"), Some(10));
+    assert_eq!(season_from_folder("S03"), Some(3));
+    assert_eq!(season_from_folder("Specials"), Some(0));
+    assert_eq!(season_from_folder("Snakes.S02E01.1080p"), None);
+
+    let path = utils::test_path("irrelevant.mkv");
+    let ep = |f: &str| {
+        Episode::from_in_season(&path, f, 3, "All My Circuits", 1.0, &allowed_exts()).unwrap()
+    };
+
+    assert_eq!(
+        ep("E04 - The Big Shed.mkv").remote_filename(),
+        "S03 E04.mkv"
+    );
+    assert_eq!(ep("Episode 4.mkv").remote_filename(), "S03 E04.mkv");
+    assert_eq!(ep("04 - The Big Shed.mkv").remote_filename(), "S03 E04.mkv");
+    // A season in the filename takes precedence
+    assert_eq!(ep("S02E01.mkv").remote_filename(), "S02 E01.mkv");
+}
+
+#[test]
+fn resolution_from_tags() {
+    assert_eq!(
+        resolution("All.My.Circuits.S01E01.1080p.WEB.mkv"),
+        Some(1080)
+    );
+    assert_eq!(resolution("All My Circuits S01E01 [720p].mkv"), Some(720));
+    assert_eq!(resolution("All.My.Circuits.S01E01.2160p.mkv"), Some(2160));
+    assert_eq!(resolution("All.My.Circuits.S01E01.4K.HDR.mkv"), Some(2160));
+    assert_eq!(resolution("S01 E01.mkv"), None);
+    assert_eq!(resolution("All.My.Circuits.S01E01.x1080p264.mkv"), None);
+}
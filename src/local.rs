@@ -1,16 +1,25 @@
 #[cfg(test)]
 mod tests;
 
+pub mod movies;
+pub mod watch;
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::fs;
 use std::fs::canonicalize;
+use std::io::{self, ErrorKind};
 use std::path::{Path, PathBuf};
 
+use chrono::NaiveDate;
 use console::Style;
+use dialoguer::{Input, Select};
+use regex::Regex;
 use serde::Deserialize;
 use thiserror::Error;
 
-use crate::episode::Episode;
-use crate::resolver::ShowResolver;
+use crate::episode::{season_from_folder, Episode};
+use crate::resolver::{EpisodeMapper, ShowResolver};
 
 #[derive(Error, Debug, PartialEq)]
 pub enum ReadError {
@@ -32,18 +41,48 @@ enum ReadShowError {
     Skipped,
 }
 
-// TODO: Prompt and/or PromptCorrection
-#[derive(Deserialize, Debug)]
+#[derive(Clone, Copy, Deserialize, Debug)]
 #[serde(rename_all = "snake_case")]
 pub enum FailureAction {
     Abort,
     Skip,
+    // Ask the user to pick the right show when it can't be resolved confidently; bad files are
+    // skipped
+    Prompt,
+}
+
+/// What to do with local episodes once they've been uploaded
+#[derive(Clone, Copy, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AfterUpload {
+    Leave,
+    // Move them into the archive dir, keeping the same folder structure
+    Archive,
+    Delete,
 }
 
+const DEFAULT_PROMPT_THRESHOLD: f64 = 0.5;
+const DEFAULT_PROMPT_CANDIDATES: usize = 5;
+const DEFAULT_SCAN_DEPTH: usize = 2;
+
+// Files which come with downloads but are never episodes; these are ignored without complaint
+const JUNK_EXTS: [&str; 12] = [
+    "nfo", "txt", "jpg", "jpeg", "png", "sfv", "md5", "url", "nzb", "par2", "db", "exe",
+];
+
 pub struct LocalReader {
     show_resolver: Box<dyn ShowResolver>,
+    episode_mapper: Option<Box<dyn EpisodeMapper>>,
     allowed_exts: Vec<String>,
     on_failure: FailureAction,
+    prompt_threshold: f64,
+    prompt_candidates: usize,
+    scan_depth: usize,
+    episode_titles: bool,
+    // Show folder names mapped to the show names they resolved to, and the subset of those
+    // which the user picked explicitly
+    resolved: RefCell<BTreeMap<String, String>>,
+    confirmed: RefCell<BTreeMap<String, String>>,
 }
 
 impl LocalReader {
@@ -53,18 +92,166 @@ impl LocalReader {
         on_failure: FailureAction,
     ) -> LocalReader {
         LocalReader {
-            show_resolver: show_resolver,
-            allowed_exts: allowed_exts,
-            on_failure: on_failure,
+            show_resolver,
+            episode_mapper: None,
+            allowed_exts,
+            on_failure,
+            prompt_threshold: DEFAULT_PROMPT_THRESHOLD,
+            prompt_candidates: DEFAULT_PROMPT_CANDIDATES,
+            scan_depth: DEFAULT_SCAN_DEPTH,
+            episode_titles: false,
+            resolved: RefCell::new(BTreeMap::new()),
+            confirmed: RefCell::new(BTreeMap::new()),
+        }
+    }
+
+    /// Use the given mapper to find the season and episode of absolutely-numbered episodes
+    pub fn with_episode_mapper(mut self, mapper: Box<dyn EpisodeMapper>) -> LocalReader {
+        self.episode_mapper = Some(mapper);
+        self
+    }
+
+    // Work out the season of an absolutely-numbered episode, if we can; otherwise leave it filed
+    // under season 1, which is how many anime are organised anyway
+    fn map_absolute(&self, ep: &mut Episode, absolute: u32) {
+        let mapped = self
+            .episode_mapper
+            .as_ref()
+            .and_then(|m| m.map_absolute(&ep.show_name, absolute));
+
+        match mapped {
+            Some((season, episode)) => {
+                ep.season_num = season;
+                ep.episode_num = episode;
+            }
+            None => println_err(&format!(
+                "{}: Couldn't find which season episode {} belongs to; it will be uploaded as S01 E{:02}.",
+                ep.local_path.display(),
+                absolute,
+                absolute
+            )),
+        }
+    }
+
+    // Look up which season and episode aired on the episode's date; if we can't, it's uploaded
+    // named by its date instead, which Kodi also understands
+    fn map_air_date(&self, ep: &mut Episode, date: NaiveDate) {
+        let mapped = self
+            .episode_mapper
+            .as_ref()
+            .and_then(|m| m.map_air_date(&ep.show_name, date));
+
+        if let Some((season, episode)) = mapped {
+            ep.season_num = season;
+            ep.episode_num = episode;
+            ep.air_date = None;
         }
     }
 
+    /// Look up the title of each episode with the episode mapper, to include in remote filenames
+    pub fn with_episode_titles(mut self, enabled: bool) -> LocalReader {
+        self.episode_titles = enabled;
+        self
+    }
+
+    // Multi-episode files get the titles of all their episodes, e.g. "Part 1 & Part 2"
+    fn find_title(&self, ep: &Episode) -> Option<String> {
+        let mapper = self.episode_mapper.as_ref()?;
+        if !self.episode_titles || ep.air_date.is_some() {
+            return None;
+        }
+
+        let titles: Vec<String> = (ep.episode_num..=ep.last_episode())
+            .filter_map(|n| mapper.episode_title(&ep.show_name, ep.season_num, n))
+            .collect();
+
+        (!titles.is_empty()).then(|| titles.join(" & "))
+    }
+
+    /// All show folder names read so far, mapped to the show names they were resolved to
+    pub fn resolved_shows(&self) -> BTreeMap<String, String> {
+        self.resolved.borrow().clone()
+    }
+
+    /// Show folder names which the user has explicitly picked a show name for
+    pub fn confirmed_shows(&self) -> BTreeMap<String, String> {
+        self.confirmed.borrow().clone()
+    }
+
+    /// Set how certain a show match must be to avoid prompting, and how many suggestions to
+    /// offer when we do; only relevant when prompting on failure
+    pub fn with_prompt(mut self, threshold: f64, candidates: usize) -> LocalReader {
+        self.prompt_threshold = threshold;
+        self.prompt_candidates = candidates;
+        self
+    }
+
+    /// Set how many levels of folders inside each show folder to look for episodes in, such as
+    /// season folders and release folders; 0 only reads files directly inside the show folder
+    pub fn with_scan_depth(mut self, depth: usize) -> LocalReader {
+        self.scan_depth = depth;
+        self
+    }
+
+    // Ask the user which show a folder refers to, offering the best suggestions we have
+    fn prompt_show(&self, show: &str) -> Result<(String, f64), ReadShowError> {
+        let candidates = self.show_resolver.candidates(show, self.prompt_candidates);
+
+        let mut items: Vec<String> = candidates
+            .iter()
+            .map(|(name, certainty)| format!("{} ({:.0}%)", name, certainty * 100.0))
+            .collect();
+        let type_idx = items.len();
+        items.push("Type a different name...".to_string());
+        items.push("Skip this TV show".to_string());
+
+        let choice = Select::new()
+            .with_prompt(format!("Which TV show is \"{}\"?", show))
+            .items(&items)
+            .default(0)
+            .interact()
+            .map_err(|_| ReadShowError::Aborted)?;
+
+        let name = if choice < type_idx {
+            candidates[choice].0.clone()
+        } else if choice == type_idx {
+            let typed: String = Input::new()
+                .with_prompt("TV show name")
+                .interact_text()
+                .map_err(|_| ReadShowError::Aborted)?;
+            typed.trim().to_string()
+        } else {
+            return Err(ReadShowError::Skipped);
+        };
+
+        if name.is_empty() {
+            return Err(ReadShowError::Skipped);
+        }
+
+        // The user has told us exactly which show this is
+        self.confirmed
+            .borrow_mut()
+            .insert(show.to_string(), name.clone());
+        Ok((name, 1.0))
+    }
+
     fn resolve_show(&self, show: &str) -> Result<(String, f64), ReadShowError> {
         match self.show_resolver.resolve(show) {
+            Some((_, certainty))
+                if matches!(self.on_failure, FailureAction::Prompt)
+                    && certainty < self.prompt_threshold =>
+            {
+                println_err(&format!("{}: Unsure of TV show name.", show));
+                self.prompt_show(show)
+            }
             Some(res) => Ok(res),
             _ => {
                 print_err(&format!("{}: Could not resolve TV show name. ", show));
                 match self.on_failure {
+                    FailureAction::Prompt => {
+                        println!();
+                        self.prompt_show(show)
+                    }
                     FailureAction::Skip => {
                         println_err("Skipping this TV show.");
                         Err(ReadShowError::Skipped)
@@ -83,25 +270,47 @@ impl LocalReader {
         path: &Path,
         show_name: &str,
         show_certainty: f64,
+        season_hint: Option<u32>,
     ) -> Result<Episode, ReadShowError> {
         let filename = path
             .file_name()
             .and_then(|f| f.to_str())
             .ok_or(ReadShowError::BadPath(path.to_path_buf()))?;
 
-        match Episode::from(
-            path,
-            filename,
-            show_name,
-            show_certainty,
-            &self.allowed_exts,
-        ) {
-            Ok(ep) => Ok(ep),
+        let parsed = match season_hint {
+            Some(season) => Episode::from_in_season(
+                path,
+                filename,
+                season,
+                show_name,
+                show_certainty,
+                &self.allowed_exts,
+            ),
+            None => Episode::from(
+                path,
+                filename,
+                show_name,
+                show_certainty,
+                &self.allowed_exts,
+            ),
+        };
+
+        match parsed {
+            Ok(mut ep) => {
+                if let Some(absolute) = ep.absolute_num {
+                    self.map_absolute(&mut ep, absolute);
+                }
+                if let Some(date) = ep.air_date {
+                    self.map_air_date(&mut ep, date);
+                }
+                ep.title = self.find_title(&ep);
+                Ok(ep)
+            }
             Err(e) => {
                 print_err(&format!("{}: {}. ", path.display(), e));
 
                 match self.on_failure {
-                    FailureAction::Skip => {
+                    FailureAction::Skip | FailureAction::Prompt => {
                         println_err("Skipping this file.");
                         Err(ReadShowError::Skipped)
                     }
@@ -122,25 +331,65 @@ impl LocalReader {
             .ok_or(ReadShowError::BadPath(abs.to_path_buf()))?;
 
         let (show_name, show_certainty) = self.resolve_show(raw_show)?;
-
-        let found_eps = fs::read_dir(dir).map_err(|_| ReadShowError::BadPath(dir.to_path_buf()))?;
+        self.resolved
+            .borrow_mut()
+            .insert(raw_show.to_string(), show_name.clone());
 
         let mut eps = vec![];
+        self.read_episodes(
+            dir,
+            &show_name,
+            show_certainty,
+            None,
+            self.scan_depth,
+            &mut eps,
+        )?;
+
+        Ok(group_subtitles(eps))
+    }
+
+    // Read episodes from a folder within a show, descending into season and release folders up to
+    // the given depth. Season folders give the season of any episodes inside which don't have one.
+    fn read_episodes(
+        &self,
+        dir: &Path,
+        show_name: &str,
+        show_certainty: f64,
+        season_hint: Option<u32>,
+        depth: usize,
+        eps: &mut Vec<Episode>,
+    ) -> Result<(), ReadShowError> {
+        let found_eps = fs::read_dir(dir).map_err(|_| ReadShowError::BadPath(dir.to_path_buf()))?;
 
         for entry in found_eps {
-            let res: Result<Episode, ReadShowError> = entry
-                .map_err(|_| ReadShowError::BadPath(dir.to_path_buf()))
-                .map(|e| e.path())
-                .and_then(|p| self.read_one(&p, &show_name, show_certainty));
+            let path = entry
+                .map_err(|_| ReadShowError::BadPath(dir.to_path_buf()))?
+                .path();
+
+            if is_junk(&path) {
+                continue;
+            }
 
-            match res {
+            if path.is_dir() {
+                if depth > 0 {
+                    let hint = path
+                        .file_name()
+                        .and_then(|f| f.to_str())
+                        .and_then(season_from_folder)
+                        .or(season_hint);
+                    self.read_episodes(&path, show_name, show_certainty, hint, depth - 1, eps)?;
+                }
+                continue;
+            }
+
+            match self.read_one(&path, show_name, show_certainty, season_hint) {
                 Ok(ep) => eps.push(ep),
                 Err(ReadShowError::Skipped) => (),
                 Err(fatal) => return Err(fatal),
             }
         }
 
-        Ok(eps)
+        Ok(())
     }
 
     pub fn read_local(&self, dir: &Path) -> Result<Vec<Episode>, ReadError> {
@@ -176,6 +425,74 @@ impl LocalReader {
     }
 }
 
+/// Move a file from within `root` to the same place within `archive_dir`, returning where it ended
+/// up
+pub fn archive_file(path: &Path, root: &Path, archive_dir: &Path) -> io::Result<PathBuf> {
+    let relative = path.strip_prefix(root).map_err(|_| {
+        io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("{} isn't in {}", path.display(), root.display()),
+        )
+    })?;
+    let dest = archive_dir.join(relative);
+
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // Renaming doesn't work across filesystems, so fall back to copying
+    if fs::rename(path, &dest).is_err() {
+        fs::copy(path, &dest)?;
+        fs::remove_file(path)?;
+    }
+
+    Ok(dest)
+}
+
+/// Remove any folders containing `path` which have been left empty, up to but not including
+/// `root`, so emptied show folders aren't read on the next sync
+pub fn remove_empty_dirs(path: &Path, root: &Path) {
+    for dir in path.ancestors().skip(1) {
+        if dir == root || !dir.starts_with(root) || fs::remove_dir(dir).is_err() {
+            break;
+        }
+    }
+}
+
+// Hidden files, samples and the likes of .nfo files which come with downloads aren't episodes
+fn is_junk(path: &Path) -> bool {
+    let sample = Regex::new(r"(?i)(?:^|[^a-z])sample(?:[^a-z]|$)").unwrap();
+
+    let Some(name) = path.file_name().and_then(|f| f.to_str()) else {
+        return false;
+    };
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+
+    name.starts_with('.')
+        || sample.is_match(name)
+        || (!path.is_dir() && JUNK_EXTS.contains(&ext.as_str()))
+}
+
+// Attach subtitle files to the video they belong to, so they're uploaded alongside it; any
+// without a matching video are kept as they are, as the video may already be on the remote
+fn group_subtitles(eps: Vec<Episode>) -> Vec<Episode> {
+    let (mut videos, subtitles): (Vec<Episode>, Vec<Episode>) =
+        eps.into_iter().partition(|ep| !ep.is_subtitle());
+
+    for sub in subtitles {
+        match videos.iter_mut().find(|v| sub.is_subtitle_for(v)) {
+            Some(video) => video.subtitles.push(sub),
+            None => videos.push(sub),
+        }
+    }
+
+    videos
+}
+
 fn print_err(msg: &str) -> () {
     let red = Style::new().red();
     print!("{}", red.apply_to(msg));
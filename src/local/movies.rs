@@ -0,0 +1,141 @@
+#[cfg(test)]
+mod tests;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{is_junk, print_err, println_err, FailureAction, ReadError};
+use crate::episode::ParseError;
+use crate::movie::Movie;
+use crate::resolver::MovieResolver;
+
+// How sure we are of a film which couldn't be checked against anything
+const CERTAINTY_UNVERIFIED: f64 = 0.8;
+
+pub struct MovieReader {
+    resolver: Option<Box<dyn MovieResolver>>,
+    allowed_exts: Vec<String>,
+    on_failure: FailureAction,
+}
+
+impl MovieReader {
+    pub fn new(
+        resolver: Option<Box<dyn MovieResolver>>,
+        allowed_exts: Vec<String>,
+        on_failure: FailureAction,
+    ) -> MovieReader {
+        MovieReader {
+            resolver,
+            allowed_exts,
+            on_failure,
+        }
+    }
+
+    fn has_allowed_ext(&self, path: &Path) -> bool {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| self.allowed_exts.iter().any(|a| a == e))
+    }
+
+    // Find the film inside a folder; if there are several candidate files, the biggest is the
+    // film and the rest are likely to be samples or extras
+    fn find_in_dir(&self, dir: &Path) -> Option<PathBuf> {
+        fs::read_dir(dir)
+            .ok()?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_file() && !is_junk(p) && self.has_allowed_ext(p))
+            .max_by_key(|p| p.metadata().map(|m| m.len()).unwrap_or(0))
+    }
+
+    fn parse(&self, path: &Path, name: &str) -> Result<Movie, ParseError> {
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .ok_or(ParseError::BadExtension)?;
+
+        let mut movie = Movie::from(path, name, ext, &self.allowed_exts)?;
+
+        match self
+            .resolver
+            .as_ref()
+            .and_then(|r| r.resolve_movie(&movie.title, movie.year))
+        {
+            Some((title, year, certainty)) => {
+                movie.title = title;
+                movie.year = year;
+                movie.certainty = certainty;
+            }
+            None => movie.certainty = CERTAINTY_UNVERIFIED,
+        }
+
+        if movie.year.is_none() {
+            return Err(ParseError::BadYear);
+        }
+
+        Ok(movie)
+    }
+
+    // Read a single entry in the movies directory, which is either a film or a folder containing
+    // one. Folder names tend to be tidier than filenames, so they're preferred for parsing.
+    fn read_one(&self, path: &Path) -> Result<Option<Movie>, ParseError> {
+        let name = |p: &Path| {
+            p.file_name()
+                .and_then(|f| f.to_str())
+                .map(String::from)
+                .ok_or(ParseError::BadPath)
+        };
+        let stem = |p: &Path| {
+            p.file_stem()
+                .and_then(|f| f.to_str())
+                .map(String::from)
+                .ok_or(ParseError::BadPath)
+        };
+
+        if path.is_dir() {
+            let Some(file) = self.find_in_dir(path) else {
+                return Ok(None);
+            };
+
+            return self
+                .parse(&file, &name(path)?)
+                .or_else(|_| self.parse(&file, &stem(&file)?))
+                .map(Some);
+        }
+
+        self.parse(path, &stem(path)?).map(Some)
+    }
+
+    pub fn read_movies(&self, dir: &Path) -> Result<Vec<Movie>, ReadError> {
+        let found = fs::read_dir(dir).map_err(|_| ReadError::Fatal)?;
+
+        let mut movies = vec![];
+
+        for entry in found {
+            let path = entry.map_err(|_| ReadError::Fatal)?.path();
+            if is_junk(&path) {
+                continue;
+            }
+
+            match self.read_one(&path) {
+                Ok(Some(m)) => movies.push(m),
+                Ok(None) => (),
+                Err(e) => {
+                    print_err(&format!("{}: {}. ", path.display(), e));
+
+                    match self.on_failure {
+                        FailureAction::Skip | FailureAction::Prompt => {
+                            println_err("Skipping this film.")
+                        }
+                        FailureAction::Abort => {
+                            println_err("Aborting!");
+                            return Err(ReadError::Aborted);
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(movies)
+    }
+}
@@ -0,0 +1,68 @@
+use super::*;
+
+use std::fs;
+
+use crate::tests as utils;
+
+fn reader(on_failure: FailureAction) -> MovieReader {
+    let exts = vec!["mkv", "mp4"].into_iter().map(String::from).collect();
+    MovieReader::new(None, exts, on_failure)
+}
+
+fn create(path: &str, size: usize) {
+    let p = utils::create_path(path);
+    fs::write(p, vec![0; size]).unwrap();
+}
+
+fn names(mut movies: Vec<Movie>) -> Vec<String> {
+    movies.sort();
+    movies.into_iter().map(|m| m.remote_name()).collect()
+}
+
+#[test]
+fn read_movies_parses_years() {
+    let dir = utils::test_path("movies-years");
+    create("movies-years/Some.Film.2010.1080p.mkv", 10);
+    create("movies-years/2001 A Space Odyssey (1968).mp4", 10);
+    create("movies-years/Tidy Name (2001)/tdy.nm.x264.mkv", 10);
+
+    let actual = names(reader(FailureAction::Abort).read_movies(&dir).unwrap());
+    assert_eq!(
+        actual,
+        vec![
+            "2001 A Space Odyssey (1968)",
+            "Some Film (2010)",
+            "Tidy Name (2001)"
+        ]
+    );
+}
+
+#[test]
+fn read_movies_ignores_junk_and_samples() {
+    let dir = utils::test_path("movies-junk");
+    create("movies-junk/Some Film (2010)/Some.Film.2010.mkv", 100);
+    create(
+        "movies-junk/Some Film (2010)/Some.Film.2010.sample.mkv",
+        1000,
+    );
+    create("movies-junk/Some Film (2010)/Some.Film.2010.nfo", 10);
+    create("movies-junk/notes.nfo", 10);
+    create("movies-junk/.hidden (2010).mkv", 10);
+
+    let movies = reader(FailureAction::Abort).read_movies(&dir).unwrap();
+    assert_eq!(movies.len(), 1);
+    assert!(movies[0].local_path.ends_with("Some.Film.2010.mkv"));
+}
+
+#[test]
+fn read_movies_requires_year() {
+    let dir = utils::test_path("movies-no-year");
+    create("movies-no-year/Some Film (2010).mkv", 10);
+    create("movies-no-year/Undated Film.mkv", 10);
+
+    let actual = names(reader(FailureAction::Skip).read_movies(&dir).unwrap());
+    assert_eq!(actual, vec!["Some Film (2010)"]);
+
+    let actual = reader(FailureAction::Abort).read_movies(&dir).unwrap_err();
+    assert_eq!(actual, ReadError::Aborted);
+}
@@ -47,7 +47,7 @@ fn setup_some_invalid(prefix: &str) -> () {
     setup_all_valid(prefix);
     let paths = vec![
         "sync.sh",
-        "The Wild Adventures of abyssal horror/spam file.txt",
+        "The Wild Adventures of abyssal horror/spam file.avi",
         "Totally non-existent show/S01 E01.mkv",
     ];
 
@@ -92,3 +92,97 @@ fn read_local_abort_on_invalid() {
     let actual = reader_abort().read_local(&prefix).unwrap_err();
     assert_eq!(actual, expected);
 }
+
+#[test]
+fn read_local_abort_on_invalid_file() {
+    let mut prefix = PathBuf::from(utils::PATH_PREFIX);
+    let test_path = "local-abort-invalid-file";
+    prefix.push(test_path);
+
+    // Every show resolves, so only the file itself can cause this
+    setup_all_valid(test_path);
+    utils::create_path(&format!(
+        "{}/Snakes are our Friends (2010)/spam file.avi",
+        test_path
+    ));
+
+    let expected = ReadError::Aborted;
+    let actual = reader_abort().read_local(&prefix).unwrap_err();
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn read_local_groups_subtitles() {
+    let mut prefix = PathBuf::from(utils::PATH_PREFIX);
+    let test_path = "local-subtitles";
+    prefix.push(test_path);
+
+    for p in [
+        "Snakes are our Friends (2010)/S07 E69.mkv",
+        "Snakes are our Friends (2010)/S07 E69.en.srt",
+        "Snakes are our Friends (2010)/S07 E69.en.forced.srt",
+        "Snakes are our Friends (2010)/S07 E70.en.srt",
+    ] {
+        utils::create_path(&format!("{}/{}", test_path, p));
+    }
+
+    let exts = vec!["mkv", "srt"].into_iter().map(String::from).collect();
+    let reader = LocalReader::new(Box::new(resolver()), exts, FailureAction::Abort);
+    let mut result = reader.read_local(&prefix).unwrap();
+    result.sort();
+
+    assert_eq!(result.len(), 2);
+    assert_eq!(result[0].remote_filename(), "S07 E69.mkv");
+    assert_eq!(result[0].subtitles.len(), 2);
+    assert_eq!(result[1].remote_filename(), "S07 E70.en.srt");
+}
+
+#[test]
+fn read_local_recursive() {
+    let mut prefix = PathBuf::from(utils::PATH_PREFIX);
+    let test_path = "local-recursive";
+    prefix.push(test_path);
+
+    for p in [
+        "Snakes are our Friends (2010)/Season 2/Snakes.S02E01.1080p/Snakes.S02E01.1080p.mkv",
+        "Snakes are our Friends (2010)/Season 2/Snakes.S02E01.1080p/Sample/snakes.s02e01.sample.mkv",
+        "Snakes are our Friends (2010)/Season 2/Snakes.S02E01.1080p/Snakes.S02E01.1080p.nfo",
+        "Snakes are our Friends (2010)/Season 3/E04 - The Big Shed.mkv",
+        "Snakes are our Friends (2010)/Season 3/Extras/Deep/S03 E99.mkv",
+    ] {
+        utils::create_path(&format!("{}/{}", test_path, p));
+    }
+
+    let mut result = reader_abort().read_local(&prefix).unwrap();
+    result.sort();
+
+    let names: Vec<String> = result.iter().map(|e| e.remote_filename()).collect();
+    assert_eq!(names, vec!["S02 E01.mkv", "S03 E04.mkv"]);
+
+    let shallow = reader_abort()
+        .with_scan_depth(0)
+        .read_local(&prefix)
+        .unwrap();
+    assert!(shallow.is_empty());
+}
+
+#[test]
+fn test_archive_file() {
+    let root = utils::test_path("local-archive/tv");
+    let archive = utils::test_path("local-archive/archive");
+    let path = utils::create_path("local-archive/tv/Some Show/Season 1/S01E01.mkv");
+    utils::create_path("local-archive/tv/Some Show/S01E02.mkv");
+
+    let dest = archive_file(&path, &root, &archive).unwrap();
+    assert_eq!(dest, archive.join("Some Show/Season 1/S01E01.mkv"));
+    assert!(dest.exists());
+    assert!(!path.exists());
+
+    // The season folder is empty now, but the show folder isn't
+    remove_empty_dirs(&path, &root);
+    assert!(!root.join("Some Show/Season 1").exists());
+    assert!(root.join("Some Show/S01E02.mkv").exists());
+
+    let outside = utils::create_path("local-archive/elsewhere.mkv");
+    assert!(archive_file(&outside, &root, &archive).is_err());
+}
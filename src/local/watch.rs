@@ -0,0 +1,184 @@
+#[cfg(test)]
+mod tests;
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Result as IoResult;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use super::is_junk;
+use crate::episode::Episode;
+
+/// Keeps track of the files in a directory between polls, to tell which new or changed files
+/// have finished being written, i.e. haven't changed size for a while
+pub struct Watcher {
+    dir: PathBuf,
+    depth: usize,
+    settle_time: Duration,
+    // Every file seen on the last poll, with its size and when it was first seen at that size
+    seen: HashMap<PathBuf, (u64, Instant)>,
+    // Files which have already been dealt with, and the size they were at the time
+    handled: HashMap<PathBuf, u64>,
+}
+
+impl Watcher {
+    /// Watch files up to `depth` levels of folders inside `dir`, considering them complete once
+    /// their size hasn't changed for `settle_time`
+    pub fn new(dir: &Path, depth: usize, settle_time: Duration) -> Watcher {
+        Watcher {
+            dir: dir.to_path_buf(),
+            depth,
+            settle_time,
+            seen: HashMap::new(),
+            handled: HashMap::new(),
+        }
+    }
+
+    /// Look for new and changed files
+    pub fn poll(&mut self) -> IoResult<()> {
+        let mut files = vec![];
+        list_files(&self.dir, self.depth, &mut files)?;
+        self.observe(files, Instant::now());
+        Ok(())
+    }
+
+    fn observe(&mut self, files: Vec<(PathBuf, u64)>, now: Instant) {
+        self.seen = files
+            .into_iter()
+            .map(|(path, size)| {
+                let since = match self.seen.get(&path) {
+                    Some((prev, since)) if *prev == size => *since,
+                    _ => now,
+                };
+                (path, (size, since))
+            })
+            .collect();
+
+        // Anything which has since been removed is new again if it comes back
+        let seen = &self.seen;
+        self.handled.retain(|path, _| seen.contains_key(path));
+    }
+
+    // New or changed files, with whether they've settled yet
+    fn changes(&self, now: Instant) -> impl Iterator<Item = (&PathBuf, bool)> {
+        self.seen
+            .iter()
+            .filter(|(path, (size, _))| self.handled.get(*path) != Some(size))
+            .map(move |(path, (_, since))| (path, now.duration_since(*since) >= self.settle_time))
+    }
+
+    /// New or changed files which have finished being written
+    pub fn settled(&self, now: Instant) -> Vec<PathBuf> {
+        let mut settled: Vec<PathBuf> = self
+            .changes(now)
+            .filter(|(_, done)| *done)
+            .map(|(path, _)| path.clone())
+            .collect();
+        settled.sort();
+        settled
+    }
+
+    /// Every file which is safe to sync: those which have finished being written, and those
+    /// already dealt with which haven't changed since. Anything else, including files which have
+    /// turned up since the last poll, may still be being written.
+    pub fn ready(&self, now: Instant) -> HashSet<PathBuf> {
+        self.seen
+            .iter()
+            .filter(|(path, (size, since))| {
+                self.handled.get(*path) == Some(size)
+                    || now.duration_since(*since) >= self.settle_time
+            })
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+
+    /// Remember that the given files have been dealt with, so they aren't reported again unless
+    /// they change
+    pub fn mark_handled(&mut self, paths: &[PathBuf]) {
+        for path in paths {
+            if let Some((size, _)) = self.seen.get(path) {
+                self.handled.insert(path.clone(), *size);
+            }
+        }
+    }
+}
+
+/// Decides when to try again after a sync fails: twice as long after each failure in a row, up to
+/// a limit, unless the files to sync have changed in the meantime
+pub struct Backoff {
+    base: Duration,
+    max: Duration,
+    failures: u32,
+    // When the last sync failed, and which files it was for
+    last_failure: Option<(Instant, Vec<PathBuf>)>,
+}
+
+impl Backoff {
+    pub fn new(base: Duration, max: Duration) -> Backoff {
+        Backoff {
+            base,
+            max,
+            failures: 0,
+            last_failure: None,
+        }
+    }
+
+    /// How long to wait after the last failure before trying the same files again
+    pub fn delay(&self) -> Duration {
+        let factor = 2u32.saturating_pow(self.failures.saturating_sub(1));
+        self.base.saturating_mul(factor).min(self.max)
+    }
+
+    /// Whether it's worth trying to sync the given files yet
+    pub fn should_try(&self, files: &[PathBuf], now: Instant) -> bool {
+        match &self.last_failure {
+            Some((at, failed)) => failed != files || now.duration_since(*at) >= self.delay(),
+            None => true,
+        }
+    }
+
+    pub fn failed(&mut self, files: &[PathBuf], now: Instant) {
+        self.failures += 1;
+        self.last_failure = Some((now, files.to_vec()));
+    }
+
+    pub fn succeeded(&mut self) {
+        self.failures = 0;
+        self.last_failure = None;
+    }
+}
+
+/// Keep only the episodes, and subtitles, whose files are in the given set, e.g. those which are
+/// ready to sync
+pub fn only_ready(eps: Vec<Episode>, ready: &HashSet<PathBuf>) -> Vec<Episode> {
+    eps.into_iter()
+        .filter(|e| ready.contains(&e.local_path))
+        .map(|mut e| {
+            e.subtitles.retain(|s| ready.contains(&s.local_path));
+            e
+        })
+        .collect()
+}
+
+fn list_files(dir: &Path, depth: usize, files: &mut Vec<(PathBuf, u64)>) -> IoResult<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+
+        if is_junk(&path) {
+            continue;
+        }
+
+        let meta = entry.metadata()?;
+        if meta.is_dir() {
+            if depth > 0 {
+                list_files(&path, depth - 1, files)?;
+            }
+        } else {
+            files.push((path, meta.len()));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,160 @@
+use super::*;
+
+use crate::tests as utils;
+
+const SETTLE: Duration = Duration::from_secs(60);
+
+fn watcher() -> Watcher {
+    Watcher::new(Path::new("/nowhere"), 2, SETTLE)
+}
+
+fn files(sizes: &[(&str, u64)]) -> Vec<(PathBuf, u64)> {
+    sizes.iter().map(|(p, s)| (PathBuf::from(p), *s)).collect()
+}
+
+#[test]
+fn test_settles_once_size_is_stable() {
+    let start = Instant::now();
+    let mut w = watcher();
+
+    w.observe(files(&[("a.mkv", 100)]), start);
+    assert!(w.settled(start).is_empty());
+    assert!(!w.ready(start).contains(Path::new("a.mkv")));
+
+    // Still growing
+    let later = start + SETTLE;
+    w.observe(files(&[("a.mkv", 200)]), later);
+    assert!(w.settled(later).is_empty());
+
+    let done = later + SETTLE;
+    w.observe(files(&[("a.mkv", 200)]), done);
+    assert_eq!(w.settled(done), vec![PathBuf::from("a.mkv")]);
+    assert!(w.ready(done).contains(Path::new("a.mkv")));
+}
+
+#[test]
+fn test_handled_files_are_not_reported_again() {
+    let start = Instant::now();
+    let done = start + SETTLE;
+    let mut w = watcher();
+
+    w.observe(files(&[("a.mkv", 100), ("b.mkv", 100)]), start);
+    w.observe(files(&[("a.mkv", 100), ("b.mkv", 100)]), done);
+    w.mark_handled(&w.settled(done));
+    assert!(w.settled(done).is_empty());
+    assert_eq!(w.ready(done).len(), 2);
+
+    // A new file arrives, and another is rewritten
+    let later = done + SETTLE;
+    w.observe(
+        files(&[("a.mkv", 100), ("b.mkv", 150), ("c.mkv", 10)]),
+        later,
+    );
+    w.observe(
+        files(&[("a.mkv", 100), ("b.mkv", 150), ("c.mkv", 10)]),
+        later + SETTLE,
+    );
+    assert_eq!(
+        w.settled(later + SETTLE),
+        vec![PathBuf::from("b.mkv"), PathBuf::from("c.mkv")]
+    );
+}
+
+#[test]
+fn test_removed_files_are_forgotten() {
+    let start = Instant::now();
+    let done = start + SETTLE;
+    let mut w = watcher();
+
+    w.observe(files(&[("a.mkv", 100)]), start);
+    w.observe(files(&[("a.mkv", 100)]), done);
+    w.mark_handled(&w.settled(done));
+
+    w.observe(vec![], done);
+    w.observe(files(&[("a.mkv", 100)]), done + SETTLE);
+    w.observe(files(&[("a.mkv", 100)]), done + SETTLE * 2);
+    assert_eq!(w.settled(done + SETTLE * 2), vec![PathBuf::from("a.mkv")]);
+}
+
+#[test]
+fn test_poll() {
+    let dir = utils::test_path("watch");
+    utils::create_path("watch/Some Show/Season 1/S01E01.mkv");
+    utils::create_path("watch/Some Show/Season 1/Extras/Deep/S01E01.mkv");
+    utils::create_path("watch/Some Show/S01E02.nfo");
+    utils::create_path("watch/Some Show/.S01E03.mkv.part");
+
+    let mut w = Watcher::new(&dir, 2, Duration::ZERO);
+    w.poll().unwrap();
+
+    let found: Vec<PathBuf> = w.settled(Instant::now());
+    assert_eq!(found, vec![dir.join("Some Show/Season 1/S01E01.mkv")]);
+}
+
+#[test]
+fn test_files_appearing_after_poll_are_not_ready() {
+    let dir = utils::test_path("watch_late");
+    let early = utils::create_path("watch_late/Some Show/S01E01.mkv");
+    let subs = utils::create_path("watch_late/Some Show/S01E01.en.srt");
+    for f in ["S01E02.mkv", "S01E02.en.srt"] {
+        let _ = fs::remove_file(dir.join("Some Show").join(f));
+    }
+
+    let mut w = Watcher::new(&dir, 2, Duration::ZERO);
+    w.poll().unwrap();
+    let ready = w.ready(Instant::now());
+
+    // Still downloading when the sync reads the folder
+    let late = utils::create_path("watch_late/Some Show/S01E02.mkv");
+    let late_subs = utils::create_path("watch_late/Some Show/S01E02.en.srt");
+
+    let exts = ["mkv", "srt"];
+    let ep = |path: &PathBuf| {
+        let filename = path.file_name().unwrap().to_str().unwrap();
+        Episode::from(path, filename, "Some Show", 1.0, &exts).unwrap()
+    };
+    let mut first = ep(&early);
+    first.subtitles = vec![ep(&subs), ep(&late_subs)];
+    let second = ep(&late);
+
+    let actual = only_ready(vec![first, second], &ready);
+    assert_eq!(actual.len(), 1);
+    assert_eq!(actual[0].local_path, early);
+    assert_eq!(actual[0].subtitles.len(), 1);
+    assert_eq!(actual[0].subtitles[0].local_path, subs);
+}
+
+#[test]
+fn test_backoff_doubles_after_each_failure() {
+    let start = Instant::now();
+    let files = vec![PathBuf::from("a.mkv")];
+    let mut b = Backoff::new(SETTLE, SETTLE * 3);
+    assert!(b.should_try(&files, start));
+
+    b.failed(&files, start);
+    assert!(!b.should_try(&files, start + SETTLE / 2));
+    assert!(b.should_try(&files, start + SETTLE));
+
+    let start = start + SETTLE;
+    b.failed(&files, start);
+    assert!(!b.should_try(&files, start + SETTLE));
+    assert!(b.should_try(&files, start + SETTLE * 2));
+
+    // Never longer than the limit
+    b.failed(&files, start);
+    b.failed(&files, start);
+    assert_eq!(b.delay(), SETTLE * 3);
+
+    b.succeeded();
+    assert!(b.should_try(&files, start));
+}
+
+#[test]
+fn test_backoff_retries_when_files_change() {
+    let start = Instant::now();
+    let mut b = Backoff::new(SETTLE, SETTLE * 3);
+
+    b.failed(&[PathBuf::from("a.mkv")], start);
+    assert!(!b.should_try(&[PathBuf::from("a.mkv")], start));
+    assert!(b.should_try(&[PathBuf::from("a.mkv"), PathBuf::from("b.mkv")], start));
+}
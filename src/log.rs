@@ -1,28 +1,198 @@
-use serde::Serialize;
+#[cfg(test)]
+mod tests;
 
-use std::fs::{create_dir_all, File};
-use std::io::{BufWriter, Result as IoResult, Write};
+use serde::{Deserialize, Serialize};
+
+use std::fmt;
+use std::fs::{self, create_dir_all, File};
+use std::io::{BufReader, BufWriter, ErrorKind, Result as IoResult, Write};
 use std::path::PathBuf;
 
+use ::log::warn;
 use chrono::{DateTime, Utc};
 
+use crate::client::Upload;
 use crate::episode::Episode;
+use crate::movie::Movie;
+
+// Where episodes `watch` wasn't sure enough of are kept, alongside the events
+const QUEUE_FILE: &str = "queue.json";
+
+/// What happened to a single upload
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    // Not finished yet; if this is logged, the sync was interrupted
+    Pending,
+    Uploaded,
+    Failed,
+    // Never started, because another upload failed
+    NotAttempted,
+    // Uploaded, then removed again by `rusttv undo`
+    Undone,
+}
+
+/// What was done with a local file after it was uploaded
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LocalAction {
+    Archived(PathBuf),
+    Deleted,
+}
+
+/// Exactly which remote files a sync creates, and which existing files it replaces, so that it
+/// can be undone
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct UploadRecord {
+    pub local: PathBuf,
+    pub remote: String,
+    pub replaces: Option<String>,
+    #[serde(default)]
+    pub size: u64,
+    // Not recorded by older versions
+    #[serde(default)]
+    pub outcome: Option<Outcome>,
+    #[serde(default)]
+    pub error: Option<String>,
+    // Nothing is done to the local file unless configured to, and the upload succeeded
+    #[serde(default)]
+    pub local_action: Option<LocalAction>,
+}
+
+impl From<&Upload> for UploadRecord {
+    fn from(upload: &Upload) -> UploadRecord {
+        UploadRecord {
+            local: upload.local.clone(),
+            remote: upload.remote.to_string(),
+            replaces: upload.replaces.as_ref().map(|r| r.to_string()),
+            size: upload.local.metadata().map(|m| m.len()).unwrap_or(0),
+            outcome: Some(Outcome::Pending),
+            error: None,
+            local_action: None,
+        }
+    }
+}
 
 #[derive(Serialize)]
 pub struct Event<'a> {
     timestamp: DateTime<Utc>,
     username: String,
     episodes: &'a Vec<Episode>,
+    movies: &'a Vec<Movie>,
+    uploads: Vec<UploadRecord>,
 }
 
 impl Event<'_> {
-    pub fn new<'a>(episodes: &'a Vec<Episode>) -> Event<'a> {
+    pub fn new<'a>(
+        episodes: &'a Vec<Episode>,
+        movies: &'a Vec<Movie>,
+        uploads: &[Upload],
+    ) -> Event<'a> {
         Event {
             timestamp: Utc::now(),
             username: whoami::username(),
             episodes: episodes,
+            movies,
+            uploads: uploads.iter().map(UploadRecord::from).collect(),
+        }
+    }
+
+    /// Record how each upload went, given results in the same order as the uploads
+    pub fn record_results<E: std::fmt::Display>(&mut self, results: &[Option<Result<(), E>>]) {
+        for (record, res) in self.uploads.iter_mut().zip(results) {
+            (record.outcome, record.error) = match res {
+                Some(Ok(())) => (Some(Outcome::Uploaded), None),
+                Some(Err(e)) => (Some(Outcome::Failed), Some(e.to_string())),
+                None => (Some(Outcome::NotAttempted), None),
+            };
+        }
+    }
+
+    /// Record what was done with each local file, in the same order as the uploads
+    pub fn record_local_actions(&mut self, actions: Vec<Option<LocalAction>>) {
+        for (record, action) in self.uploads.iter_mut().zip(actions) {
+            record.local_action = action;
+        }
+    }
+}
+
+/// An event read back from the event log
+#[derive(Deserialize, Serialize)]
+pub struct LoggedEvent {
+    pub timestamp: DateTime<Utc>,
+    pub username: String,
+    pub episodes: Vec<Episode>,
+    #[serde(default)]
+    pub movies: Vec<Movie>,
+    // Not recorded by older versions
+    #[serde(default)]
+    pub uploads: Vec<UploadRecord>,
+}
+
+/// How a logged sync went overall
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RunOutcome {
+    Complete,
+    Failed,
+    Interrupted,
+    // Undone by `rusttv undo`, in part or in full
+    Undone,
+    // Logged by an older version, which didn't record outcomes
+    Unknown,
+}
+
+impl fmt::Display for RunOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let desc = match self {
+            RunOutcome::Complete => "complete",
+            RunOutcome::Failed => "failed",
+            RunOutcome::Interrupted => "interrupted",
+            RunOutcome::Undone => "undone",
+            RunOutcome::Unknown => "unknown",
+        };
+
+        write!(f, "{}", desc)
+    }
+}
+
+impl LoggedEvent {
+    pub fn total_bytes(&self) -> u64 {
+        self.uploads.iter().map(|u| u.size).sum()
+    }
+
+    pub fn outcome(&self) -> RunOutcome {
+        let outcomes: Option<Vec<Outcome>> = self.uploads.iter().map(|u| u.outcome).collect();
+
+        match outcomes {
+            _ if self.uploads.is_empty() => RunOutcome::Unknown,
+            None => RunOutcome::Unknown,
+            Some(o) if o.contains(&Outcome::Undone) => RunOutcome::Undone,
+            Some(o) if o.contains(&Outcome::Failed) => RunOutcome::Failed,
+            Some(o) if o.contains(&Outcome::Pending) => RunOutcome::Interrupted,
+            Some(_) => RunOutcome::Complete,
         }
     }
+
+    /// Check whether any of the episodes synced belong to a show, or any of the films synced
+    /// have the given title, ignoring case and allowing partial names
+    pub fn involves_show(&self, show: &str) -> bool {
+        let show = show.to_lowercase();
+        let shows = self.episodes.iter().map(|e| &e.show_name);
+        let films = self.movies.iter().map(|m| &m.title);
+
+        shows
+            .chain(films)
+            .any(|name| name.to_lowercase().contains(&show))
+    }
+}
+
+/// An episode `watch` left for a manual sync to confirm, because it wasn't sure enough of the show
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct QueuedEpisode {
+    pub local: PathBuf,
+    pub show: String,
+    pub certainty: f64,
+    pub queued_at: DateTime<Utc>,
 }
 
 pub struct Logger {
@@ -35,15 +205,75 @@ impl Logger {
     }
 
     pub fn log_event(&self, e: &Event) -> IoResult<()> {
+        self.write(&e.timestamp, e)
+    }
+
+    /// Overwrite a previously logged event, e.g. to record that it was undone
+    pub fn update_event(&self, e: &LoggedEvent) -> IoResult<()> {
+        self.write(&e.timestamp, e)
+    }
+
+    // Events are stored one per file, named by when they started
+    fn write<T: Serialize>(&self, timestamp: &DateTime<Utc>, e: &T) -> IoResult<()> {
         let mut path = self.log_path.clone();
         create_dir_all(&path)?;
 
-        let filename = format!("{}.json", &e.timestamp.format("%Y%m%d_%H%M%S"));
+        let filename = format!("{}.json", timestamp.format("%Y%m%d_%H%M%S"));
         path.push(filename);
 
         let file = File::create(&path)?;
         let mut writer = BufWriter::new(file);
-        serde_json::to_writer(&mut writer, &e)?;
+        serde_json::to_writer(&mut writer, e)?;
+
+        writer.flush()
+    }
+
+    /// Read back all logged events, oldest first. Files which can't be parsed are skipped.
+    pub fn read_events(&self) -> IoResult<Vec<LoggedEvent>> {
+        let entries = match fs::read_dir(&self.log_path) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e),
+        };
+
+        let mut events = vec![];
+
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "json") || path.ends_with(QUEUE_FILE) {
+                continue;
+            }
+
+            let reader = BufReader::new(File::open(&path)?);
+            match serde_json::from_reader::<_, LoggedEvent>(reader) {
+                Ok(e) => events.push(e),
+                Err(e) => warn!("Skipping unreadable event log {}: {}", path.display(), e),
+            }
+        }
+
+        events.sort_by_key(|e| e.timestamp);
+        Ok(events)
+    }
+
+    /// Read back the episodes waiting to be confirmed, leaving out any whose files have gone
+    pub fn read_queue(&self) -> IoResult<Vec<QueuedEpisode>> {
+        let file = match File::open(self.log_path.join(QUEUE_FILE)) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e),
+        };
+
+        let queue: Vec<QueuedEpisode> = serde_json::from_reader(BufReader::new(file))?;
+        Ok(queue.into_iter().filter(|q| q.local.exists()).collect())
+    }
+
+    /// Replace the episodes waiting to be confirmed
+    pub fn write_queue(&self, queue: &[QueuedEpisode]) -> IoResult<()> {
+        create_dir_all(&self.log_path)?;
+
+        let file = File::create(self.log_path.join(QUEUE_FILE))?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer(&mut writer, queue)?;
 
         writer.flush()
     }
@@ -0,0 +1,187 @@
+use super::*;
+
+fn event(json: &str) -> LoggedEvent {
+    serde_json::from_str(json).unwrap()
+}
+
+fn upload(outcome: &str, size: u64) -> String {
+    format!(
+        concat!(
+            r#"{{"local": "/tmp/a.mkv", "remote": "/tv/a.mkv", "replaces": null, "#,
+            r#""size": {}, "outcome": {}}}"#
+        ),
+        size, outcome
+    )
+}
+
+fn with_uploads(uploads: &[String]) -> LoggedEvent {
+    event(&format!(
+        concat!(
+            r#"{{"timestamp": "2024-03-01T20:00:00Z", "username": "fry", "episodes": [], "#,
+            r#""uploads": [{}]}}"#
+        ),
+        uploads.join(", ")
+    ))
+}
+
+#[test]
+fn test_old_event_has_unknown_outcome() {
+    let e = event(r#"{"timestamp": "2020-01-01T00:00:00Z", "username": "fry", "episodes": []}"#);
+
+    assert!(e.movies.is_empty());
+    assert!(e.uploads.is_empty());
+    assert_eq!(e.total_bytes(), 0);
+    assert_eq!(e.outcome(), RunOutcome::Unknown);
+}
+
+#[test]
+fn test_outcome() {
+    let uploaded = upload(r#""uploaded""#, 100);
+    let failed = upload(r#""failed""#, 20);
+    let pending = upload(r#""pending""#, 3);
+    let skipped = upload(r#""not_attempted""#, 4);
+    let missing = upload("null", 5);
+    let undone = upload(r#""undone""#, 50);
+
+    let cases = vec![
+        (vec![uploaded.clone()], RunOutcome::Complete, 100),
+        (
+            vec![uploaded.clone(), failed.clone(), skipped],
+            RunOutcome::Failed,
+            124,
+        ),
+        (
+            vec![uploaded.clone(), pending.clone()],
+            RunOutcome::Interrupted,
+            103,
+        ),
+        (vec![failed, pending], RunOutcome::Failed, 23),
+        (vec![uploaded.clone(), missing], RunOutcome::Unknown, 105),
+        (vec![uploaded, undone], RunOutcome::Undone, 150),
+    ];
+
+    for (uploads, expected, bytes) in cases {
+        let e = with_uploads(&uploads);
+        assert_eq!(e.outcome(), expected);
+        assert_eq!(e.total_bytes(), bytes);
+    }
+}
+
+#[test]
+fn test_record_results() {
+    let dir = crate::tests::test_path("log/record");
+    let files: Vec<Upload> = ["a.mkv", "b.mkv", "c.mkv"]
+        .iter()
+        .map(|f| Upload {
+            local: dir.join(f),
+            remote: format!("/tv/{}", f).into(),
+            replaces: None,
+        })
+        .collect();
+
+    let episodes = vec![];
+    let movies = vec![];
+    let mut e = Event::new(&episodes, &movies, &files);
+    assert!(e
+        .uploads
+        .iter()
+        .all(|u| u.outcome == Some(Outcome::Pending)));
+
+    e.record_results(&[Some(Ok(())), Some(Err("broken pipe")), None]);
+
+    let outcomes: Vec<_> = e.uploads.iter().map(|u| u.outcome).collect();
+    assert_eq!(
+        outcomes,
+        vec![
+            Some(Outcome::Uploaded),
+            Some(Outcome::Failed),
+            Some(Outcome::NotAttempted)
+        ]
+    );
+    assert_eq!(e.uploads[1].error.as_deref(), Some("broken pipe"));
+}
+
+#[test]
+fn test_involves_show() {
+    let path = crate::tests::create_path("log/show/S01E01.mkv");
+    let ep = Episode::from(&path, "S01E01.mkv", "Futurama", 1.0, &["mkv"]).unwrap();
+
+    let mut e = with_uploads(&[]);
+    e.episodes.push(ep);
+
+    assert!(e.involves_show("Futurama"));
+    assert!(e.involves_show("futur"));
+    assert!(!e.involves_show("Simpsons"));
+
+    e.movies.push(Movie {
+        local_path: PathBuf::from("/tmp/The Simpsons Movie (2007).mkv"),
+        title: "The Simpsons Movie".to_string(),
+        year: Some(2007),
+        certainty: 1.0,
+        ext: "mkv".to_string(),
+    });
+    assert!(e.involves_show("Simpsons"));
+}
+
+#[test]
+fn test_local_action_round_trip() {
+    let mut record = UploadRecord {
+        local: PathBuf::from("/tmp/a.mkv"),
+        remote: "/tv/a.mkv".to_string(),
+        replaces: None,
+        size: 1,
+        outcome: Some(Outcome::Uploaded),
+        error: None,
+        local_action: Some(LocalAction::Archived(PathBuf::from("/archive/a.mkv"))),
+    };
+
+    let json = serde_json::to_string(&record).unwrap();
+    let actual: UploadRecord = serde_json::from_str(&json).unwrap();
+    assert_eq!(actual.local_action, record.local_action);
+
+    record.local_action = Some(LocalAction::Deleted);
+    let json = serde_json::to_string(&record).unwrap();
+    let actual: UploadRecord = serde_json::from_str(&json).unwrap();
+    assert_eq!(actual.local_action, Some(LocalAction::Deleted));
+}
+
+#[test]
+fn test_update_event() {
+    let logger = Logger::new(crate::tests::test_path("log/update"));
+    let mut e = with_uploads(&[upload(r#""uploaded""#, 10)]);
+    logger.update_event(&e).unwrap();
+
+    e.uploads[0].outcome = Some(Outcome::Undone);
+    logger.update_event(&e).unwrap();
+
+    let events = logger.read_events().unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].outcome(), RunOutcome::Undone);
+}
+
+#[test]
+fn test_queue_round_trip() {
+    let logger = Logger::new(crate::tests::test_path("log/queue"));
+    let queued = |local: PathBuf| QueuedEpisode {
+        local,
+        show: "Futurama".to_string(),
+        certainty: 0.6,
+        queued_at: "2024-03-01T20:00:00Z".parse().unwrap(),
+    };
+    let present = queued(crate::tests::create_path("log/queue-files/S01E01.mkv"));
+    let gone = queued(crate::tests::test_path("log/queue-files/S01E02.mkv"));
+
+    logger.write_queue(&[present.clone(), gone]).unwrap();
+    logger.update_event(&with_uploads(&[])).unwrap();
+
+    // Files which have been moved or deleted since don't need confirming
+    assert_eq!(logger.read_queue().unwrap(), vec![present]);
+    // Nor is the queue mistaken for an event
+    assert_eq!(logger.read_events().unwrap().len(), 1);
+}
+
+#[test]
+fn test_no_queue() {
+    let logger = Logger::new(crate::tests::test_path("log/no-queue"));
+    assert!(logger.read_queue().unwrap().is_empty());
+}
@@ -1,38 +1,61 @@
 #[cfg(test)]
 pub mod tests;
 
+pub mod cli;
 pub mod client;
 pub mod config;
+pub mod diff;
 pub mod episode;
 pub mod local;
 pub mod log;
+pub mod movie;
 pub mod resolver;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::error;
+use std::fs;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
 
+use chrono::{Local, NaiveDate, Utc};
+use clap::Parser;
 use console::Style;
 use dialoguer::Confirm;
 use flexi_logger::{Cleanup, Criterion, Duplicate, FileSpec, Logger, Naming, detailed_format};
+use indicatif::HumanBytes;
 use ::log::{info, error};
 use proc_lock::proc_lock;
 use typed_path::Utf8UnixPathBuf;
 
+use crate::cli::{AliasCommand, Cli, Command};
 use crate::client::osmc::OsmcClient;
-use crate::client::{Auth as SshAuth, SshClient};
-use crate::config::{Config, Osmc as OsmcConfig, Tmdb as TmdbConfig};
+use crate::client::pool::upload_all;
+use crate::client::throttle::Throttle;
+use crate::client::{Auth as SshAuth, RemoteFile, SshClient, TransferOptions, Upload, TRASH_DIR};
+use crate::config::{Config, Osmc as OsmcConfig, Overrides, Tmdb as TmdbConfig};
+use crate::diff::{diff_eps, DiffOptions};
 use crate::episode::Episode;
-use crate::local::LocalReader;
-use crate::log::{Event as LogEvent, Logger as ProcessLogger};
+use crate::local::movies::MovieReader;
+use crate::local::watch::{only_ready, Backoff, Watcher};
+use crate::local::{archive_file, remove_empty_dirs, AfterUpload, FailureAction, LocalReader};
+use crate::log::{
+    Event as LogEvent, LocalAction, Logger as ProcessLogger, Outcome as UploadOutcome,
+    QueuedEpisode, RunOutcome, UploadRecord,
+};
+use crate::movie::Movie;
+use crate::resolver::alias::{AliasResolver, AliasStore};
 use crate::resolver::multi::MultiResolver;
 use crate::resolver::strsim::StrsimResolver;
 use crate::resolver::tmdb::TmdbResolver;
-use crate::resolver::ShowResolver;
+use crate::resolver::{EpisodeMapper, MovieResolver, ShowResolver};
 
 type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
 
+// The longest `watch` waits before trying a failed sync again
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60 * 60);
+
 macro_rules! warn {
     ($msg:expr) => {
         let yellow = Style::new().yellow();
@@ -43,8 +66,8 @@ macro_rules! warn {
 fn get_remote_eps(
     client: &mut SshClient,
     local_eps: &Vec<Episode>,
-) -> Result<HashMap<String, Vec<String>>> {
-    let mut by_show: HashMap<String, Vec<String>> = HashMap::new();
+) -> Result<HashMap<String, Vec<RemoteFile>>> {
+    let mut by_show: HashMap<String, Vec<RemoteFile>> = HashMap::new();
 
     for e in local_eps {
         if by_show.contains_key(&e.show_name) {
@@ -56,18 +79,53 @@ fn get_remote_eps(
     Ok(by_show)
 }
 
-fn get_resolver<T: AsRef<str>>(known_shows: &[T], tmdb: &TmdbConfig) -> Box<dyn ShowResolver> {
-    let strsim_resolver = StrsimResolver::new(known_shows);
+fn get_resolver<T: AsRef<str>>(
+    known_shows: &[T],
+    aliases: &AliasStore,
+    tmdb: &TmdbConfig,
+) -> Box<dyn ShowResolver> {
+    // Names the user has already approved always take precedence
+    let mut resolvers: Vec<Box<dyn ShowResolver>> = vec![
+        Box::new(AliasResolver::new(aliases)),
+        Box::new(StrsimResolver::new(known_shows)),
+    ];
+
+    if tmdb.enabled {
+        let token = tmdb.token.as_ref().expect("Missing TMDB token in config!");
+        resolvers.push(Box::new(TmdbResolver::new(
+            &tmdb.protocol,
+            &tmdb.host,
+            token,
+        )));
+    }
+
+    Box::new(MultiResolver::new(resolvers))
+}
 
+fn get_episode_mapper(tmdb: &TmdbConfig) -> Option<Box<dyn EpisodeMapper>> {
     if !tmdb.enabled {
-        return Box::new(strsim_resolver);
+        return None;
     }
     let token = tmdb.token.as_ref().expect("Missing TMDB token in config!");
-    let tmdb_resolver = TmdbResolver::new(&tmdb.protocol, &tmdb.host, &token);
+    Some(Box::new(TmdbResolver::new(
+        &tmdb.protocol,
+        &tmdb.host,
+        token,
+    )))
+}
 
-    let resolvers: Vec<Box<dyn ShowResolver>> =
-        vec![Box::new(strsim_resolver), Box::new(tmdb_resolver)];
-    Box::new(MultiResolver::new(resolvers))
+// Remember the given folder name -> show name mappings for future runs
+fn remember_aliases(aliases: &mut AliasStore, mappings: &BTreeMap<String, String>) {
+    let mut changed = false;
+    for (local, show) in mappings {
+        changed |= aliases.insert(local, show);
+    }
+
+    if changed {
+        if let Err(e) = aliases.save() {
+            error!("Failed to save aliases: {}", e);
+        }
+    }
 }
 
 fn prompt_confirm() -> bool {
@@ -77,19 +135,6 @@ fn prompt_confirm() -> bool {
         .unwrap()
 }
 
-// Filter the parsed local episodes down to only those which aren't already present in the remote
-fn diff_eps(local: Vec<Episode>, remote: HashMap<String, Vec<String>>) -> Vec<Episode> {
-    local
-        .into_iter()
-        .filter(|ep| {
-            remote
-                .get(&ep.show_name)
-                .map(|eps| !eps.contains(&ep.remote_filename()))
-                .unwrap_or(false)
-        })
-        .collect()
-}
-
 fn osmc_refresh(cfg: &OsmcConfig) -> () {
     eprintln!("");
     eprintln!("");
@@ -120,17 +165,90 @@ fn osmc_refresh(cfg: &OsmcConfig) -> () {
     };
 }
 
-#[proc_lock(name = "rusttv.lock")]
-fn perform_sync(conf: Config) -> Result<()> {
-    let remote = &conf.remote;
-    let complete = || {
-        if conf.ui.block_closing {
-            println!("");
-            println!("Finished. Press enter to exit.");
-            io::stdin().read_line(&mut String::new()).unwrap();
+fn osmc_clean(cfg: &OsmcConfig) {
+    eprintln!();
+    eprint!("Asking OSMC to clean up its library... ");
+
+    let client = OsmcClient::new(
+        &cfg.protocol,
+        &cfg.host,
+        cfg.port,
+        &cfg.prefix,
+        &cfg.username,
+        &cfg.password,
+    );
+    match client.trigger_clean() {
+        Ok(_) => eprintln!("[ {} ]", Style::new().green().apply_to("OK")),
+        Err(e) => {
+            eprintln!("[ {} ]", Style::new().red().apply_to("FAILED"));
+            error!("OSMC clean failed: {}", e);
         }
-        Ok(())
-    };
+    }
+}
+
+// Describe exactly what a sync would do to the remote, without doing any of it
+fn print_plan(client: &mut SshClient, files: &[Upload], conf: &Config) -> Result<()> {
+    let cyan = Style::new().cyan();
+
+    let mut new_dirs: BTreeSet<Utf8UnixPathBuf> = BTreeSet::new();
+    for upload in files {
+        new_dirs.extend(client.dirs_to_create(&upload.remote)?);
+    }
+
+    println!();
+    println!("Dry run; the following files would be uploaded:");
+    for upload in files {
+        println!(
+            "  {} {} {}",
+            upload.local.display(),
+            cyan.apply_to("--->"),
+            upload.remote
+        );
+    }
+
+    let replaced: Vec<&Utf8UnixPathBuf> =
+        files.iter().filter_map(|u| u.replaces.as_ref()).collect();
+    if !replaced.is_empty() {
+        println!();
+        println!(
+            "The following files would be replaced, and moved to {}:",
+            TRASH_DIR
+        );
+        for r in replaced {
+            println!("  {}", r);
+        }
+    }
+
+    println!();
+    if new_dirs.is_empty() {
+        println!("No new directories would be created.");
+    } else {
+        println!("The following directories would be created:");
+        for d in &new_dirs {
+            println!("  {}", d);
+        }
+    }
+
+    println!();
+    match (conf.local.after_upload, &conf.local.archive_dir) {
+        (AfterUpload::Archive, Some(dir)) => {
+            println!("Uploaded episodes would then be moved to {}.", dir)
+        }
+        (AfterUpload::Delete, _) => println!("Uploaded episodes would then be deleted locally."),
+        _ => (),
+    }
+    if conf.osmc.enable_refresh {
+        println!("OSMC would then be asked to refresh its library.");
+    } else {
+        println!("OSMC would not be refreshed.");
+    }
+
+    warn!("Dry run; nothing was changed.");
+    Ok(())
+}
+
+fn connect(conf: &Config) -> Result<SshClient> {
+    let remote = &conf.remote;
 
     let auth = match (&remote.privkey, &remote.password) {
         (Some(privkey), _) => SshAuth::Privkey(privkey.to_string()),
@@ -144,64 +262,799 @@ fn perform_sync(conf: Config) -> Result<()> {
         &remote.username,
         &auth,
         &Utf8UnixPathBuf::from(&remote.tv_dir),
+        &TransferOptions {
+            verify: conf.transfer.verify,
+            buffer_size: conf.transfer.buffer_size,
+        },
     )?;
-    let known_shows = client.list_shows()?;
 
-    println!("Found {} TV shows on remote host", known_shows.len());
+    if let Some(movie_dir) = &remote.movie_dir {
+        client = client.with_movie_dir(&Utf8UnixPathBuf::from(movie_dir));
+    }
+    Ok(client)
+}
 
-    let show_resolver = get_resolver(&known_shows, &conf.validation.tmdb);
+// Read local films, if configured, and filter them down to those not already on the remote
+fn get_sync_movies(conf: &Config, client: &mut SshClient) -> Result<Vec<Movie>> {
+    let Some(movie_dir) = &conf.local.movie_dir else {
+        return Ok(vec![]);
+    };
 
-    let reader = LocalReader::new(
-        show_resolver,
+    let tmdb = &conf.validation.tmdb;
+    let resolver: Option<Box<dyn MovieResolver>> = if tmdb.enabled {
+        let token = tmdb.token.as_ref().expect("Missing TMDB token in config!");
+        Some(Box::new(TmdbResolver::new(
+            &tmdb.protocol,
+            &tmdb.host,
+            token,
+        )))
+    } else {
+        None
+    };
+
+    let reader = MovieReader::new(
+        resolver,
         conf.validation.allowed_exts.clone(),
         conf.validation.on_failure,
     );
+    let local = reader.read_movies(&PathBuf::from(movie_dir))?;
+    let remote = client.list_movies()?;
+
+    Ok(local
+        .into_iter()
+        .filter(|m| !remote.contains(&m.remote_name()))
+        .collect())
+}
+
+fn episode_reader(
+    conf: &Config,
+    known_shows: &[String],
+    aliases: &AliasStore,
+    on_failure: FailureAction,
+) -> LocalReader {
+    let show_resolver = get_resolver(known_shows, aliases, &conf.validation.tmdb);
+
+    let reader = LocalReader::new(
+        show_resolver,
+        conf.validation.allowed_exts.clone(),
+        on_failure,
+    )
+    .with_prompt(
+        conf.validation.prompt_threshold,
+        conf.validation.prompt_candidates,
+    )
+    .with_scan_depth(conf.local.scan_depth)
+    .with_episode_titles(conf.validation.tmdb.episode_titles);
+
+    match get_episode_mapper(&conf.validation.tmdb) {
+        Some(mapper) => reader.with_episode_mapper(mapper),
+        None => reader,
+    }
+}
+
+fn print_episodes(eps: &[Episode]) {
+    if eps.is_empty() {
+        return;
+    }
+
+    println!("Syncing the following episodes:");
+    for e in eps
+        .iter()
+        .flat_map(|e| std::iter::once(e).chain(&e.subtitles))
+    {
+        println!("{}", e);
+        if let Some(replaces) = &e.replaces {
+            warn!(format!("    replacing {}", replaces));
+        }
+    }
+}
+
+// Where to upload each episode, along with its subtitles
+fn episode_uploads(conf: &Config, eps: &[Episode]) -> Vec<Upload> {
+    let tv_dir = Utf8UnixPathBuf::from(&conf.remote.tv_dir);
+
+    eps.iter()
+        .flat_map(|e| std::iter::once(e).chain(&e.subtitles))
+        .map(|e| Upload {
+            local: e.local_path.clone(),
+            remote: tv_dir.join(e.remote_subpath(&conf.remote.episode_template)),
+            replaces: e
+                .replaces
+                .as_ref()
+                .map(|r| tv_dir.join(&e.show_name).join(r)),
+        })
+        .collect()
+}
+
+// Upload everything, recording the sync in the event log as we go so it can be reviewed or undone,
+// and refresh the library if anything was uploaded
+fn upload_files(
+    conf: &Config,
+    mut client: SshClient,
+    sync_eps: &Vec<Episode>,
+    sync_movies: &Vec<Movie>,
+    files: &[Upload],
+) -> Result<()> {
+    let observer = conf
+        .ui
+        .progress
+        .observer(conf.ui.progress_file.as_deref().map(Path::new))?;
+
+    let logger = ProcessLogger::new(conf.log.local_path.clone());
+    let mut event = LogEvent::new(sync_eps, sync_movies, files);
+    let _ = logger.log_event(&event);
+    let remote_paths: Vec<Utf8UnixPathBuf> = files.iter().map(|u| u.remote.clone()).collect();
+
+    // Leave partial uploads of episodes we're about to sync in place so they can be resumed
+    client.wipe_temp(&remote_paths)?;
+
+    // Each concurrent upload gets its own SSH session
+    let mut clients = vec![client];
+    for _ in 1..conf.transfer.concurrency.min(files.len()) {
+        clients.push(connect(conf)?);
+    }
+
+    info!("Syncing episodes: [{:?}]", sync_eps);
+    info!("Syncing films: [{:?}]", sync_movies);
+    println!();
+    let throttle = match conf.transfer.max_rate {
+        Some(rate) => Throttle::new(rate, conf.transfer.max_rate_hours),
+        None => Throttle::default(),
+    };
+    let results = upload_all(clients, files, &throttle, observer.as_ref())?;
+
+    event.record_results(&results);
+    let _ = logger.log_event(&event);
+
+    let uploaded: Vec<bool> = results.iter().map(|r| matches!(r, Some(Ok(())))).collect();
+    event.record_local_actions(tidy_local(conf, files, &uploaded));
+    let _ = logger.log_event(&event);
+
+    // Whatever did make it should show up in the library, even if something else failed
+    if conf.osmc.enable_refresh && uploaded.contains(&true) {
+        osmc_refresh(&conf.osmc);
+    }
+
+    if let Some(Err(e)) = results.into_iter().flatten().find(|r| r.is_err()) {
+        return Err(e.into());
+    }
+
+    Ok(())
+}
+
+// Archive or delete local episodes which were uploaded successfully, if configured to
+fn tidy_local(conf: &Config, files: &[Upload], uploaded: &[bool]) -> Vec<Option<LocalAction>> {
+    let mut actions: Vec<Option<LocalAction>> = files.iter().map(|_| None).collect();
+    if conf.local.after_upload == AfterUpload::Leave || !uploaded.contains(&true) {
+        return actions;
+    }
+
+    let mut client = match connect(conf) {
+        Ok(client) => client,
+        Err(e) => {
+            error!(
+                "Couldn't check uploads, so local files were left alone: {}",
+                e
+            );
+            return actions;
+        }
+    };
+    let tv_dir = PathBuf::from(&conf.local.tv_dir);
+
+    for (i, upload) in files.iter().enumerate() {
+        // Films are always left alone
+        if uploaded[i] && upload.local.starts_with(&tv_dir) {
+            actions[i] = tidy_file(conf, &mut client, upload, &tv_dir);
+        }
+    }
+
+    actions
+}
+
+fn tidy_file(
+    conf: &Config,
+    client: &mut SshClient,
+    upload: &Upload,
+    tv_dir: &Path,
+) -> Option<LocalAction> {
+    // Make sure the remote copy is all there before letting go of the local one
+    let local_size = upload.local.metadata().map(|m| m.len()).ok();
+    let remote_size = client.remote_size(&upload.remote).ok();
+    if local_size.is_none() || local_size != remote_size {
+        error!(
+            "Couldn't confirm {} was fully uploaded, so it was left alone",
+            upload.local.display()
+        );
+        return None;
+    }
+
+    let action = match (conf.local.after_upload, &conf.local.archive_dir) {
+        (AfterUpload::Archive, Some(archive_dir)) => {
+            archive_file(&upload.local, tv_dir, Path::new(archive_dir)).map(LocalAction::Archived)
+        }
+        (AfterUpload::Delete, _) => fs::remove_file(&upload.local).map(|_| LocalAction::Deleted),
+        _ => return None,
+    };
+
+    match action {
+        Ok(action) => {
+            info!("{}: {:?}", upload.local.display(), action);
+            remove_empty_dirs(&upload.local, tv_dir);
+            Some(action)
+        }
+        Err(e) => {
+            error!("Couldn't tidy up {}: {}", upload.local.display(), e);
+            None
+        }
+    }
+}
+
+#[proc_lock(name = "rusttv.lock")]
+fn perform_sync(conf: Config) -> Result<()> {
+    let complete = || {
+        if conf.ui.block_closing {
+            println!("");
+            println!("Finished. Press enter to exit.");
+            io::stdin().read_line(&mut String::new()).unwrap();
+        }
+        Ok(())
+    };
+
+    let mut client = connect(&conf)?;
+    let known_shows = client.list_shows()?;
+
+    println!("Found {} TV shows on remote host", known_shows.len());
+
+    let mut aliases = AliasStore::load(&conf.validation.aliases_file)?;
+    let reader = episode_reader(&conf, &known_shows, &aliases, conf.validation.on_failure);
     let local_eps = reader.read_local(&PathBuf::from(&conf.local.tv_dir))?;
+    remember_aliases(&mut aliases, &reader.confirmed_shows());
 
     let remote_eps = get_remote_eps(&mut client, &local_eps)?;
 
-    let mut sync_eps: Vec<Episode> = diff_eps(local_eps, remote_eps);
+    let diff_options = DiffOptions {
+        on_existing: conf.validation.on_existing,
+        on_upgrade: conf.validation.on_upgrade,
+    };
+    let mut sync_eps: Vec<Episode> = diff_eps(local_eps, &remote_eps, &diff_options);
     sync_eps.sort();
 
-    if sync_eps.len() == 0 {
+    let mut sync_movies: Vec<Movie> = get_sync_movies(&conf, &mut client)?;
+    sync_movies.sort();
+
+    if sync_eps.is_empty() && sync_movies.is_empty() {
         warn!("Nothing to sync!");
         return complete();
     }
 
-    println!("Syncing the following episodes:");
-    for e in &sync_eps {
-        println!("{}", e);
+    print_episodes(&sync_eps);
+
+    // Anything `watch` left for us to confirm is synced along with everything else, but worth
+    // pointing out so the show can be checked
+    let logger = ProcessLogger::new(conf.log.local_path.clone());
+    let queue = logger.read_queue()?;
+    let confirming: Vec<QueuedEpisode> = queue
+        .iter()
+        .filter(|q| sync_eps.iter().any(|e| e.local_path == q.local))
+        .cloned()
+        .collect();
+    if !confirming.is_empty() {
+        warn!("These were left by `watch` to confirm, as it wasn't sure of their shows:");
+        print_queued(&confirming);
     }
 
-    if conf.validation.prompt_confirmation && !prompt_confirm() {
-        warn!("Aborting.");
+    if !sync_movies.is_empty() {
+        println!("Syncing the following films:");
+        for m in &sync_movies {
+            println!("{}", m);
+        }
+    }
+
+    let mut files = episode_uploads(&conf, &sync_eps);
+
+    if let Some(movie_dir) = &conf.remote.movie_dir {
+        files.extend(sync_movies.iter().map(|m| {
+            let mut remote_path = Utf8UnixPathBuf::from(movie_dir);
+            remote_path.push(m.remote_subpath());
+            Upload {
+                local: m.local_path.clone(),
+                remote: remote_path,
+                replaces: None,
+            }
+        }));
+    }
+
+    if conf.transfer.dry_run {
+        print_plan(&mut client, &files, &conf)?;
         return complete();
     }
 
+    if conf.validation.prompt_confirmation {
+        if !prompt_confirm() {
+            warn!("Aborting.");
+            return complete();
+        }
+
+        // The user has approved the show names of everything listed, so remember them
+        let approved: BTreeMap<String, String> = reader
+            .resolved_shows()
+            .into_iter()
+            .filter(|(_, show)| sync_eps.iter().any(|e| &e.show_name == show))
+            .collect();
+        remember_aliases(&mut aliases, &approved);
+    }
+
+    upload_files(&conf, client, &sync_eps, &sync_movies, &files)?;
+
+    // Everything queued has now been confirmed, or is already on the remote; only keep whatever
+    // `watch` has queued since
+    let remaining: Vec<QueuedEpisode> = logger
+        .read_queue()?
+        .into_iter()
+        .filter(|q| !queue.contains(q))
+        .collect();
+    logger.write_queue(&remaining)?;
+
+    complete()
+}
+
+// Sync whatever has finished downloading without asking anything, leaving alone any files which
+// are still being written. Episodes of shows we aren't sure enough of are left for a manual sync
+// to confirm, and returned.
+#[proc_lock(name = "rusttv.lock")]
+fn sync_unattended(conf: &Config, ready: &HashSet<PathBuf>) -> Result<Vec<Episode>> {
+    let mut client = connect(conf)?;
+    let known_shows = client.list_shows()?;
+    let aliases = AliasStore::load(&conf.validation.aliases_file)?;
+
+    // Nobody is around to pick a show, so any which can't be resolved are skipped
+    let on_failure = match conf.validation.on_failure {
+        FailureAction::Prompt => FailureAction::Skip,
+        action => action,
+    };
+    let reader = episode_reader(conf, &known_shows, &aliases, on_failure);
+
+    // Files may have appeared since the watcher last looked, so only those it knows are finished
+    // are synced
+    let local_eps = only_ready(
+        reader.read_local(&PathBuf::from(&conf.local.tv_dir))?,
+        ready,
+    );
+    let remote_eps = get_remote_eps(&mut client, &local_eps)?;
+
+    let (sure, unsure): (Vec<Episode>, Vec<Episode>) = local_eps
+        .into_iter()
+        .partition(|e| e.show_certainty >= conf.watch.min_certainty);
+
+    let diff_options = DiffOptions {
+        on_existing: conf.validation.on_existing,
+        on_upgrade: conf.validation.on_upgrade,
+    }
+    .unattended();
+    let mut sync_eps = diff_eps(sure, &remote_eps, &diff_options);
+    sync_eps.sort();
+
+    // Only worth confirming if they'd actually be uploaded
+    let mut queued = diff_eps(unsure, &remote_eps, &diff_options);
+    queued.sort();
+
+    if sync_eps.is_empty() {
+        return Ok(queued);
+    }
+
+    print_episodes(&sync_eps);
+    let files = episode_uploads(conf, &sync_eps);
+
+    if conf.transfer.dry_run {
+        print_plan(&mut client, &files, conf)?;
+        return Ok(queued);
+    }
+
+    upload_files(conf, client, &sync_eps, &vec![], &files)?;
+
+    Ok(queued)
+}
+
+// Add any episodes which aren't queued for confirmation yet, returning whether there were any
+fn queue_unsure(queue: &mut Vec<QueuedEpisode>, unsure: &[Episode]) -> bool {
+    let before = queue.len();
+
+    for e in unsure {
+        if queue.iter().any(|q| q.local == e.local_path) {
+            continue;
+        }
+
+        ::log::warn!(
+            "{}: not sure this is {}; run a sync to confirm it",
+            e.local_path.display(),
+            e.show_name
+        );
+        queue.push(QueuedEpisode {
+            local: e.local_path.clone(),
+            show: e.show_name.clone(),
+            certainty: e.show_certainty,
+            queued_at: Utc::now(),
+        });
+    }
+
+    queue.len() > before
+}
+
+fn print_queued(queue: &[QueuedEpisode]) {
+    for q in queue {
+        println!(
+            "{}  {}  (maybe {}, {:.0}% sure)",
+            q.queued_at
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S"),
+            q.local.display(),
+            q.show,
+            q.certainty * 100.0
+        );
+    }
+}
+
+fn watch(conf: &Config) -> Result<()> {
+    let poll_interval = Duration::from_secs(conf.watch.poll_interval);
+
+    // Show folders, and as many folders inside them as are read for episodes
+    let mut watcher = Watcher::new(
+        Path::new(&conf.local.tv_dir),
+        conf.local.scan_depth + 1,
+        Duration::from_secs(conf.watch.settle_time),
+    );
+    // Episodes left for a sync to confirm, kept between runs so they aren't forgotten
+    let logger = ProcessLogger::new(conf.log.local_path.clone());
+    let mut queue = logger.read_queue()?;
+    let mut backoff = Backoff::new(poll_interval, MAX_RETRY_DELAY);
+
+    println!("Watching {} for new episodes...", conf.local.tv_dir);
+
+    loop {
+        if let Err(e) = watcher.poll() {
+            error!("Couldn't read {}: {}", conf.local.tv_dir, e);
+        }
+
+        let now = Instant::now();
+        let ready = watcher.settled(now);
+
+        if !ready.is_empty() && backoff.should_try(&ready, now) {
+            info!("Finished downloading: {:?}", ready);
+
+            match sync_unattended(conf, &watcher.ready(now)) {
+                Ok(unsure) => {
+                    if queue_unsure(&mut queue, &unsure) {
+                        if let Err(e) = logger.write_queue(&queue) {
+                            error!("Couldn't save episodes to confirm: {}", e);
+                        }
+                    }
+                    watcher.mark_handled(&ready);
+                    backoff.succeeded();
+                }
+                // Everything is still there to try again, once something has changed or after
+                // waiting a while
+                Err(e) => {
+                    backoff.failed(&ready, now);
+                    error!("Sync failed, trying again in {:?}: {}", backoff.delay(), e);
+                }
+            }
+        }
+
+        thread::sleep(poll_interval);
+    }
+}
+
+fn list_remote(conf: &Config, show: Option<&str>) -> Result<()> {
+    let mut client = connect(conf)?;
+
+    let entries = match show {
+        Some(s) => client
+            .list_episodes(s)?
+            .into_iter()
+            .map(|f| f.path)
+            .collect(),
+        None => client.list_shows()?,
+    };
+    for e in entries {
+        println!("{}", e);
+    }
+
+    Ok(())
+}
+
+fn show_history(
+    conf: &Config,
+    show: Option<&str>,
+    since: Option<NaiveDate>,
+    until: Option<NaiveDate>,
+) -> Result<()> {
+    let logger = ProcessLogger::new(conf.log.local_path.clone());
+    let events = logger.read_events()?;
+
+    let queue = logger.read_queue()?;
+    let queued: Vec<QueuedEpisode> = queue
+        .into_iter()
+        .filter(|q| show.is_none_or(|s| q.show.to_lowercase().contains(&s.to_lowercase())))
+        .collect();
+    if !queued.is_empty() {
+        warn!("Waiting for a sync to confirm:");
+        print_queued(&queued);
+        println!();
+    }
+
+    if events.is_empty() {
+        warn!("No syncs have been logged yet.");
+        return Ok(());
+    }
+
+    // Syncs keep their numbers when filtered, so they can be passed to `history` and `undo`
+    let matching = events.iter().enumerate().filter(|(_, e)| {
+        let date = e.timestamp.with_timezone(&Local).date_naive();
+
+        show.is_none_or(|s| e.involves_show(s))
+            && since.is_none_or(|d| date >= d)
+            && until.is_none_or(|d| date <= d)
+    });
+
+    for (i, e) in matching {
+        println!(
+            "{:>4}  {}  {:<16} {} episode(s), {} film(s), {:>10}  {}",
+            i + 1,
+            e.timestamp
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S"),
+            e.username,
+            e.episodes.len(),
+            e.movies.len(),
+            HumanBytes(e.total_bytes()).to_string(),
+            e.outcome()
+        );
+    }
+
+    Ok(())
+}
+
+fn show_run(conf: &Config, run: usize) -> Result<()> {
+    let logger = ProcessLogger::new(conf.log.local_path.clone());
+    let events = logger.read_events()?;
+
+    let Some(e) = run.checked_sub(1).and_then(|i| events.get(i)) else {
+        warn!("No such sync; see `rusttv history` for the list of syncs.");
+        return Ok(());
+    };
+
+    println!(
+        "Sync {} at {} by {}: {}",
+        run,
+        e.timestamp
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M:%S"),
+        e.username,
+        e.outcome()
+    );
+
+    if !e.episodes.is_empty() {
+        println!();
+        println!("Episodes:");
+        for ep in &e.episodes {
+            println!("{}", ep);
+        }
+    }
+    if !e.movies.is_empty() {
+        println!();
+        println!("Films:");
+        for m in &e.movies {
+            println!("{}", m);
+        }
+    }
+
+    if !e.uploads.is_empty() {
+        println!();
+        println!("Uploads ({}):", HumanBytes(e.total_bytes()));
+    }
+    for u in &e.uploads {
+        let status = match u.outcome {
+            Some(UploadOutcome::Uploaded) => Style::new().green().apply_to("OK"),
+            Some(UploadOutcome::Failed) => Style::new().red().apply_to("FAILED"),
+            Some(UploadOutcome::Pending) => Style::new().yellow().apply_to("INTERRUPTED"),
+            Some(UploadOutcome::NotAttempted) => Style::new().yellow().apply_to("SKIPPED"),
+            Some(UploadOutcome::Undone) => Style::new().yellow().apply_to("UNDONE"),
+            None => Style::new().apply_to("?"),
+        };
+        println!("  [ {} ] {} ({})", status, u.remote, HumanBytes(u.size));
+
+        if let Some(replaced) = &u.replaces {
+            println!("      replacing {}", replaced);
+        }
+        if let Some(error) = &u.error {
+            println!("      {}", Style::new().red().apply_to(error));
+        }
+        match &u.local_action {
+            Some(LocalAction::Archived(to)) => println!("      archived to {}", to.display()),
+            Some(LocalAction::Deleted) => println!("      deleted locally"),
+            None => (),
+        }
+    }
+
+    Ok(())
+}
+
+fn undo_sync(conf: &Config, run: Option<usize>, delete: bool) -> Result<()> {
     let logger = ProcessLogger::new(conf.log.local_path.clone());
-    let _ = logger.log_event(&LogEvent::new(&sync_eps));
+    let mut events = logger.read_events()?;
+
+    let event = match run {
+        Some(n) => n.checked_sub(1).and_then(|i| events.get_mut(i)),
+        None => events.last_mut(),
+    };
+    let Some(event) = event else {
+        warn!("No such sync; see `rusttv history` for the list of syncs.");
+        return Ok(());
+    };
+    if event.uploads.is_empty() {
+        warn!("That sync didn't record which files it uploaded, so it can't be undone.");
+        return Ok(());
+    }
 
-    client.wipe_temp()?;
+    // Only files which were uploaded, and haven't been undone already, are touched
+    let uploaded = |u: &UploadRecord| u.outcome == Some(UploadOutcome::Uploaded);
+    if !event.uploads.iter().any(uploaded) {
+        if event.outcome() == RunOutcome::Undone {
+            warn!("That sync has already been undone.");
+        } else {
+            warn!("None of the files in that sync were uploaded, so there's nothing to undo.");
+        }
+        return Ok(());
+    }
 
-    info!("Syncing episodes: [{:?}]", &sync_eps);
-    for e in &sync_eps {
-        println!("\n");
-        println!("{}", e.remote_subpath());
+    let action = if delete {
+        "deleted"
+    } else {
+        "moved to the trash"
+    };
+    println!(
+        "The following files uploaded at {} will be {}:",
+        event
+            .timestamp
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M:%S"),
+        action
+    );
+    for u in event.uploads.iter().filter(|u| uploaded(u)) {
+        println!("  {}", u.remote);
+        if let Some(replaced) = &u.replaces {
+            println!("    and {} restored from the trash", replaced);
+        }
+    }
 
-        let mut remote_path = Utf8UnixPathBuf::from(&conf.remote.tv_dir);
-        remote_path.push(&e.remote_subpath());
-        client.upload_file(&e.local_path, &remote_path)?;
+    if conf.validation.prompt_confirmation && !prompt_confirm() {
+        warn!("Aborting.");
+        return Ok(());
     }
 
+    let mut client = connect(conf)?;
+    let since = event.timestamp;
+    let mut res = Ok(());
+    for u in event.uploads.iter_mut().filter(|u| uploaded(u)) {
+        let remote = Utf8UnixPathBuf::from(&u.remote);
+        let replaced = u.replaces.as_ref().map(Utf8UnixPathBuf::from);
+
+        match client.undo_upload(&remote, replaced.as_deref(), since, delete) {
+            Ok(found) => {
+                if !found {
+                    warn!(format!("{} was already gone", u.remote));
+                }
+                u.outcome = Some(UploadOutcome::Undone);
+            }
+            Err(e) => {
+                res = Err(e);
+                break;
+            }
+        }
+    }
+
+    // Record what was undone, even if not everything was, so it's never undone twice
+    logger.update_event(event)?;
+    res?;
+    println!("[ {} ]", Style::new().green().apply_to("OK"));
+
+    // Drop library entries for the removed files, and pick up any restored ones
     if conf.osmc.enable_refresh {
+        osmc_clean(&conf.osmc);
         osmc_refresh(&conf.osmc);
     }
 
-    complete()
+    Ok(())
+}
+
+fn check_config(conf: &Config) -> Result<()> {
+    println!("Read config from {}", conf.source.display());
+
+    let mut client = connect(conf)?;
+    let shows = client.list_shows()?;
+
+    println!(
+        "Connected to {} and found {} TV shows in {}",
+        conf.remote.host,
+        shows.len(),
+        conf.remote.tv_dir
+    );
+    println!("[ {} ]", Style::new().green().apply_to("OK"));
+
+    Ok(())
+}
+
+fn manage_aliases(conf: &Config, action: AliasCommand) -> Result<()> {
+    let mut aliases = AliasStore::load(&conf.validation.aliases_file)?;
+
+    match action {
+        AliasCommand::List => {
+            if aliases.aliases().is_empty() {
+                warn!("No aliases have been saved yet.");
+            }
+            for (local, show) in aliases.aliases() {
+                println!(
+                    "{} {} {}",
+                    local,
+                    Style::new().cyan().apply_to("--->"),
+                    show
+                );
+            }
+        }
+        AliasCommand::Add { folder, show } => {
+            aliases.insert(&folder, &show);
+            aliases.save()?;
+        }
+        AliasCommand::Remove { folder } => {
+            if !aliases.remove(&folder) {
+                warn!(format!("No alias found for {}", folder));
+            }
+            aliases.save()?;
+        }
+    }
+
+    Ok(())
+}
+
+fn run(cli: Cli) -> Result<()> {
+    let overrides = Overrides {
+        skip_confirmation: cli.yes,
+        progress: cli.progress,
+        progress_file: cli.progress_file,
+        tv_dir: cli.local_dir,
+    };
+    let mut conf = config::read(cli.config.as_deref(), overrides)?;
+
+    match cli.command.unwrap_or(Command::Sync { dry_run: false }) {
+        Command::Sync { dry_run } => {
+            conf.transfer.dry_run |= dry_run;
+            perform_sync(conf)
+        }
+        Command::Plan => {
+            conf.transfer.dry_run = true;
+            perform_sync(conf)
+        }
+        Command::Watch => watch(&conf),
+        Command::ListRemote { show } => list_remote(&conf, show.as_deref()),
+        Command::History { run: Some(run), .. } => show_run(&conf, run),
+        Command::History {
+            run: None,
+            show,
+            since,
+            until,
+        } => show_history(&conf, show.as_deref(), since, until),
+        Command::Undo { run, delete } => undo_sync(&conf, run, delete),
+        Command::CheckConfig => check_config(&conf),
+        Command::Refresh => {
+            osmc_refresh(&conf.osmc);
+            Ok(())
+        }
+        Command::Aliases { action } => manage_aliases(&conf, action.unwrap_or(AliasCommand::List)),
+    }
 }
 
 fn main() {
-    let conf = config::read();
+    let cli = Cli::parse();
 
     Logger::try_with_str("debug")
         .unwrap()
@@ -212,12 +1065,16 @@ fn main() {
             Naming::Timestamps,
             Cleanup::KeepLogFiles(20)
         )
-        .duplicate_to_stderr(Duplicate::Warn)
+        .duplicate_to_stderr(if cli.verbose {
+            Duplicate::Info
+        } else {
+            Duplicate::Warn
+        })
         .format(detailed_format)
         .start()
         .unwrap();
 
-    perform_sync(conf)
+    run(cli)
         .map_err(|e| {
             error!("{}", e);
             std::process::exit(1);
@@ -0,0 +1,122 @@
+#[cfg(test)]
+mod tests;
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use typed_path::Utf8UnixPathBuf;
+
+use crate::episode::{describe_upload, ParseError};
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Movie {
+    pub local_path: PathBuf,
+    pub title: String,
+    pub year: Option<u32>,
+    pub certainty: f64,
+    pub ext: String,
+}
+
+impl fmt::Display for Movie {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            describe_upload(&self.local_path, &self.remote_name(), self.certainty)
+        )
+    }
+}
+
+impl PartialEq for Movie {
+    fn eq(&self, other: &Self) -> bool {
+        self.title == other.title && self.year == other.year && self.ext == other.ext
+    }
+}
+impl Eq for Movie {}
+
+impl Ord for Movie {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.title
+            .cmp(&other.title)
+            .then(self.year.cmp(&other.year))
+            .then(self.ext.cmp(&other.ext))
+    }
+}
+impl PartialOrd for Movie {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Movie {
+    // Tidy up a title taken from a filename; e.g. Some.Film_Name -> Some Film Name
+    fn clean_title(raw: &str) -> String {
+        let spaced = raw.replace(['.', '_'], " ");
+        let words: Vec<&str> = spaced.split_whitespace().collect();
+        words.join(" ").trim_matches(['-', ' ']).to_string()
+    }
+
+    // Parse a title and year from a name without its extension, e.g. a filename stem or folder
+    // name like Some.Film.2010.1080p or Some Film (2010)
+    fn parse_name(name: &str) -> (String, Option<u32>) {
+        // The title is greedy so that the last year-like token wins, to cope with titles which
+        // contain a year themselves
+        let pattern = Regex::new(
+            r"^(?<title>.+)(?:[\s\.\-_]+[\(\[]?|[\(\[])(?<year>(?:19|20)[0-9]{2})[\)\]]?(?:[\s\.\-_\[\(].*)?$",
+        )
+        .unwrap();
+
+        match pattern.captures(name) {
+            Some(caps) => {
+                let title = Self::clean_title(&caps["title"]);
+                let year = caps["year"].parse::<u32>().ok();
+                (title, year)
+            }
+            None => (Self::clean_title(name), None),
+        }
+    }
+
+    /// Parse a film from its name (a filename stem, or the name of a folder containing the film)
+    /// and extension
+    pub fn from<T: AsRef<str>>(
+        path: &Path,
+        name: &str,
+        ext: &str,
+        allowed_exts: &[T],
+    ) -> Result<Movie, ParseError> {
+        if !allowed_exts.iter().any(|e| e.as_ref() == ext) {
+            return Err(ParseError::BadExtension);
+        }
+
+        let (title, year) = Self::parse_name(name);
+        if title.is_empty() {
+            return Err(ParseError::BadFilename);
+        }
+
+        Ok(Movie {
+            local_path: path.to_path_buf(),
+            title,
+            year,
+            certainty: 0.0,
+            ext: ext.to_string(),
+        })
+    }
+
+    pub fn remote_name(&self) -> String {
+        match self.year {
+            Some(y) => format!("{} ({})", self.title, y),
+            None => self.title.clone(),
+        }
+    }
+    pub fn remote_filename(&self) -> String {
+        format!("{}.{}", self.remote_name(), self.ext)
+    }
+    pub fn remote_subpath(&self) -> Utf8UnixPathBuf {
+        let mut p = Utf8UnixPathBuf::from(self.remote_name());
+        p.push(self.remote_filename());
+        p
+    }
+}
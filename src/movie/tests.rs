@@ -0,0 +1,59 @@
+use super::*;
+
+use crate::tests as utils;
+
+fn allowed_exts() -> Vec<String> {
+    vec!["mkv", "mp4"].into_iter().map(String::from).collect()
+}
+
+#[test]
+fn parse_name_variants() {
+    for (name, title, year) in [
+        ("The Slurm Story (2011)", "The Slurm Story", Some(2011)),
+        (
+            "Attack.of.the.Killer.App.2010.1080p.BluRay",
+            "Attack of the Killer App",
+            Some(2010),
+        ),
+        ("Bender_Big_Score_2007", "Bender Big Score", Some(2007)),
+        (
+            "Into the Wild Green Yonder [2009] [1080p]",
+            "Into the Wild Green Yonder",
+            Some(2009),
+        ),
+        ("Space.Pilot.3000.1999.720p", "Space Pilot 3000", Some(1999)),
+        ("Benders Game", "Benders Game", None),
+    ] {
+        let (actual_title, actual_year) = Movie::parse_name(name);
+        assert_eq!(actual_title, title);
+        assert_eq!(actual_year, year);
+    }
+}
+
+#[test]
+fn valid_movie() {
+    let path = utils::test_path("irrelevant.mkv");
+
+    let actual = Movie::from(
+        &path,
+        "Benders.Big.Score.2007.1080p",
+        "mkv",
+        &allowed_exts(),
+    )
+    .unwrap();
+
+    assert_eq!(actual.title, "Benders Big Score");
+    assert_eq!(actual.year, Some(2007));
+    assert_eq!(
+        actual.remote_subpath(),
+        Utf8UnixPathBuf::from("Benders Big Score (2007)/Benders Big Score (2007).mkv")
+    );
+}
+
+#[test]
+fn movie_bad_extension() {
+    let path = utils::test_path("irrelevant.nfo");
+
+    let actual = Movie::from(&path, "Benders Big Score (2007)", "nfo", &allowed_exts());
+    assert_eq!(actual.unwrap_err(), ParseError::BadExtension);
+}
@@ -1,8 +1,33 @@
+pub mod alias;
 pub mod multi;
 pub mod strsim;
 pub mod tmdb;
 
+use chrono::NaiveDate;
+
 pub trait ShowResolver {
     // Resolve a show name and return the resolved name and a certainty index (0-1)
     fn resolve(&self, name: &str) -> Option<(String, f64)>;
+
+    // List up to `limit` possible matches for a show name, best first, for the user to pick from
+    fn candidates(&self, name: &str, limit: usize) -> Vec<(String, f64)> {
+        self.resolve(name).into_iter().take(limit).collect()
+    }
+}
+
+pub trait EpisodeMapper {
+    // Map an absolute episode number of a show onto a season and episode number
+    fn map_absolute(&self, show: &str, absolute: u32) -> Option<(u32, u32)>;
+
+    // Find the season and episode number of a show's episode which aired on the given date
+    fn map_air_date(&self, show: &str, date: NaiveDate) -> Option<(u32, u32)>;
+
+    // Find the title of an episode of a show, made safe to use in a filename
+    fn episode_title(&self, show: &str, season: u32, episode: u32) -> Option<String>;
+}
+
+pub trait MovieResolver {
+    // Resolve a film's title and (if known) release year, returning the canonical title and year
+    // along with a certainty index (0-1)
+    fn resolve_movie(&self, title: &str, year: Option<u32>) -> Option<(String, Option<u32>, f64)>;
 }
@@ -0,0 +1,92 @@
+#[cfg(test)]
+mod tests;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::ShowResolver;
+
+#[derive(Default, Deserialize, Serialize)]
+struct AliasFile {
+    #[serde(default)]
+    aliases: BTreeMap<String, String>,
+}
+
+/// Persistent mapping of local folder names to the show names the user has approved for them
+pub struct AliasStore {
+    path: PathBuf,
+    aliases: BTreeMap<String, String>,
+}
+
+impl AliasStore {
+    /// Load aliases from the given file; a missing file is treated as an empty store
+    pub fn load(path: &Path) -> io::Result<AliasStore> {
+        let aliases = match fs::read_to_string(path) {
+            Ok(raw) => {
+                toml::from_str::<AliasFile>(&raw)
+                    .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?
+                    .aliases
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e),
+        };
+
+        Ok(AliasStore {
+            path: path.to_path_buf(),
+            aliases,
+        })
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let file = AliasFile {
+            aliases: self.aliases.clone(),
+        };
+        let raw = toml::to_string(&file).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        fs::write(&self.path, raw)
+    }
+
+    pub fn aliases(&self) -> &BTreeMap<String, String> {
+        &self.aliases
+    }
+
+    /// Record an alias, returning whether anything changed. Aliases which map a name to itself
+    /// are pointless and are ignored.
+    pub fn insert(&mut self, local: &str, show: &str) -> bool {
+        if local == show || self.aliases.get(local).is_some_and(|s| s == show) {
+            return false;
+        }
+        self.aliases.insert(local.to_string(), show.to_string());
+        true
+    }
+
+    pub fn remove(&mut self, local: &str) -> bool {
+        self.aliases.remove(local).is_some()
+    }
+}
+
+/// Resolve show names the user has already approved, with complete certainty
+pub struct AliasResolver {
+    aliases: BTreeMap<String, String>,
+}
+
+impl AliasResolver {
+    pub fn new(store: &AliasStore) -> AliasResolver {
+        AliasResolver {
+            aliases: store.aliases().clone(),
+        }
+    }
+}
+
+impl ShowResolver for AliasResolver {
+    fn resolve(&self, name: &str) -> Option<(String, f64)> {
+        self.aliases.get(name).map(|show| (show.clone(), 1.0))
+    }
+}
@@ -0,0 +1,34 @@
+use super::*;
+
+use crate::tests as utils;
+
+#[test]
+fn load_missing_file_is_empty() {
+    let store = AliasStore::load(&utils::test_path("aliases-missing/aliases.toml")).unwrap();
+    assert!(store.aliases().is_empty());
+}
+
+#[test]
+fn save_and_reload() {
+    let path = utils::test_path("aliases-roundtrip/aliases.toml");
+    let _ = fs::remove_file(&path);
+
+    let mut store = AliasStore::load(&path).unwrap();
+    assert!(store.insert("all.my.circuits", "All My Circuits"));
+    assert!(!store.insert("all.my.circuits", "All My Circuits"));
+    store.save().unwrap();
+
+    let reloaded = AliasStore::load(&path).unwrap();
+    let resolver = AliasResolver::new(&reloaded);
+
+    let expected = Some(("All My Circuits".to_string(), 1.0));
+    assert_eq!(resolver.resolve("all.my.circuits"), expected);
+    assert_eq!(resolver.resolve("calculon"), None);
+}
+
+#[test]
+fn insert_ignores_identity() {
+    let mut store = AliasStore::load(&utils::test_path("aliases-identity/aliases.toml")).unwrap();
+    assert!(!store.insert("All My Circuits", "All My Circuits"));
+    assert!(store.aliases().is_empty());
+}
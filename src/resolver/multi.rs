@@ -1,3 +1,6 @@
+#[cfg(test)]
+mod tests;
+
 use super::ShowResolver;
 
 pub struct MultiResolver {
@@ -22,4 +25,20 @@ impl ShowResolver for MultiResolver {
         }
         None
     }
+
+    // Gather candidates from every resolver, in resolver order, ignoring duplicates
+    fn candidates(&self, name: &str, limit: usize) -> Vec<(String, f64)> {
+        let mut found: Vec<(String, f64)> = vec![];
+
+        for r in &self.resolvers {
+            for (candidate, certainty) in r.candidates(name, limit) {
+                if !found.iter().any(|(f, _)| *f == candidate) {
+                    found.push((candidate, certainty));
+                }
+            }
+        }
+
+        found.truncate(limit);
+        found
+    }
 }
@@ -0,0 +1,52 @@
+use super::*;
+
+use crate::resolver::strsim::StrsimResolver;
+
+// Always suggests the same shows, like a search of an online database might
+struct FixedResolver(Vec<&'static str>);
+
+impl ShowResolver for FixedResolver {
+    fn resolve(&self, _name: &str) -> Option<(String, f64)> {
+        None
+    }
+
+    fn candidates(&self, _name: &str, limit: usize) -> Vec<(String, f64)> {
+        self.0
+            .iter()
+            .take(limit)
+            .map(|s| (s.to_string(), 0.1))
+            .collect()
+    }
+}
+
+#[test]
+fn candidates_from_every_resolver() {
+    let known_shows = vec![
+        "All My Circuits",
+        "Everybody Loves Hypnotoad",
+        "Calculon (2010)",
+        "Calculon: A Calculon Story",
+        "The Scary Door",
+        "Ask Dr. Zoidberg",
+        "Single Female Lawyer",
+    ];
+    let resolver = MultiResolver::new(vec![
+        Box::new(StrsimResolver::new(&known_shows)),
+        Box::new(FixedResolver(vec!["Calculon Returns", "Calculon (2010)"])),
+    ]);
+
+    let actual: Vec<String> = resolver
+        .candidates("calculon", 5)
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+
+    assert_eq!(
+        actual,
+        vec![
+            "Calculon (2010)",
+            "Calculon: A Calculon Story",
+            "Calculon Returns"
+        ]
+    );
+}
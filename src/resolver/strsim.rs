@@ -7,6 +7,8 @@ use super::ShowResolver;
 
 const SIM_THRESHOLD_PERFECT: f64 = 0.9;
 const SIM_THRESHOLD_GOOD: f64 = 0.7;
+// Anything less similar isn't worth suggesting, and would crowd out suggestions from elsewhere
+const SIM_THRESHOLD_CANDIDATE: f64 = 0.6;
 
 pub struct StrsimResolver {
     known_shows: Vec<String>,
@@ -47,4 +49,17 @@ impl ShowResolver for StrsimResolver {
 
         None
     }
+
+    fn candidates(&self, name: &str, limit: usize) -> Vec<(String, f64)> {
+        let mut scored: Vec<(String, f64)> = self
+            .known_shows
+            .iter()
+            .map(|known| (known.clone(), strsim::jaro(name, known)))
+            .filter(|(_, certainty)| *certainty >= SIM_THRESHOLD_CANDIDATE)
+            .collect();
+
+        scored.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        scored.truncate(limit);
+        scored
+    }
 }
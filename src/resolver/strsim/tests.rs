@@ -45,3 +45,35 @@ fn resolve_fuzzy() {
         assert_eq!(actual, expected);
     }
 }
+
+#[test]
+fn candidates_best_first() {
+    let resolver = get_resolver();
+
+    let actual: Vec<String> = resolver
+        .candidates("calculon", 2)
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+
+    assert_eq!(
+        actual,
+        vec!["Calculon (2010)", "Calculon: A Calculon Story"]
+    );
+}
+
+#[test]
+fn candidates_skip_poor_matches() {
+    let resolver = get_resolver();
+
+    let actual: Vec<String> = resolver
+        .candidates("calculon", 10)
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+
+    assert_eq!(
+        actual,
+        vec!["Calculon (2010)", "Calculon: A Calculon Story"]
+    );
+}
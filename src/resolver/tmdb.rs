@@ -1,13 +1,46 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
 use regex::Regex;
 use serde_json::Value as JsValue;
 use ureq;
 
-use super::ShowResolver;
+use super::{EpisodeMapper, MovieResolver, ShowResolver};
+use crate::episode::absolute_to_seasonal;
+
+// The parts of a show's details we care about
+struct TmdbShow {
+    id: u64,
+    seasons: Vec<TmdbSeason>,
+}
+
+struct TmdbSeason {
+    number: u32,
+    episode_count: u32,
+    air_date: Option<NaiveDate>,
+}
+
+struct TmdbEpisode {
+    number: u32,
+    air_date: Option<NaiveDate>,
+    name: Option<String>,
+}
+
+// Episodes of a season, by show id and season number
+type SeasonEpisodes = HashMap<(u64, u32), Option<Vec<TmdbEpisode>>>;
 
 pub struct TmdbResolver {
     protocol: String,
     host: String,
     token: String,
+    // Show details by show name, and episodes by show id and season, so we only look each up once
+    shows: RefCell<HashMap<String, Option<TmdbShow>>>,
+    episodes: RefCell<SeasonEpisodes>,
+}
+
+fn parse_date(value: &JsValue) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value.as_str()?, "%Y-%m-%d").ok()
 }
 
 impl TmdbResolver {
@@ -16,6 +49,8 @@ impl TmdbResolver {
             protocol: protocol.to_string(),
             host: host.to_string(),
             token: token.to_string(),
+            shows: RefCell::new(HashMap::new()),
+            episodes: RefCell::new(HashMap::new()),
         }
     }
 
@@ -34,22 +69,218 @@ impl TmdbResolver {
         name.replace("/", " ").replace("\\", " ")
     }
 
-    fn get_first_match(&self, name: &str) -> Option<String> {
-        let url = format!("{}://{}/3/search/tv", self.protocol, self.host);
-        let req = ureq::get(&url)
-            .set("Authorization", &format!("Bearer {}", self.token))
+    // Episode titles end up in filenames, so also drop characters which aren't allowed in them on
+    // common filesystems, e.g. "Who Are You?: Part 1" -> "Who Are You - Part 1"
+    fn sanitise_title(title: &str) -> String {
+        let cleaned = Self::sanitise_name(title)
+            .replace(':', " -")
+            .replace(['*', '?', '"', '<', '>', '|'], "");
+
+        cleaned.split_whitespace().collect::<Vec<&str>>().join(" ")
+    }
+
+    fn search(&self, name: &str) -> Option<Vec<String>> {
+        let req = self
+            .get("search/tv")
             .query("query", &Self::strip_year(name));
 
         let res = req.call().ok()?;
 
+        let names = res
+            .into_json::<JsValue>()
+            .ok()?
+            .get("results")?
+            .as_array()?
+            .iter()
+            .filter_map(|r| r.get("name")?.as_str().map(Self::sanitise_name))
+            .collect();
+
+        Some(names)
+    }
+
+    fn get(&self, path: &str) -> ureq::Request {
+        let url = format!("{}://{}/3/{}", self.protocol, self.host, path);
+        ureq::get(&url).set("Authorization", &format!("Bearer {}", self.token))
+    }
+
+    fn find_show_id(&self, name: &str) -> Option<u64> {
+        let res = self
+            .get("search/tv")
+            .query("query", &Self::strip_year(name))
+            .call()
+            .ok()?;
+
         res.into_json::<JsValue>()
             .ok()?
             .get("results")?
             .as_array()?
             .first()?
-            .get("name")?
-            .as_str()
-            .map(Self::sanitise_name)
+            .get("id")?
+            .as_u64()
+    }
+
+    // Get the regular seasons of a show, in order; specials are ignored
+    fn fetch_show(&self, name: &str) -> Option<TmdbShow> {
+        let id = self.find_show_id(name)?;
+        let res = self.get(&format!("tv/{}", id)).call().ok()?;
+
+        let mut seasons: Vec<TmdbSeason> = res
+            .into_json::<JsValue>()
+            .ok()?
+            .get("seasons")?
+            .as_array()?
+            .iter()
+            .filter_map(|s| {
+                Some(TmdbSeason {
+                    number: s.get("season_number")?.as_u64()? as u32,
+                    episode_count: s.get("episode_count")?.as_u64()? as u32,
+                    air_date: s.get("air_date").and_then(parse_date),
+                })
+            })
+            .filter(|s| s.number > 0)
+            .collect();
+
+        seasons.sort_by_key(|s| s.number);
+        Some(TmdbShow { id, seasons })
+    }
+
+    fn fetch_episodes(&self, id: u64, season: u32) -> Option<Vec<TmdbEpisode>> {
+        let res = self
+            .get(&format!("tv/{}/season/{}", id, season))
+            .call()
+            .ok()?;
+
+        let episodes = res
+            .into_json::<JsValue>()
+            .ok()?
+            .get("episodes")?
+            .as_array()?
+            .iter()
+            .filter_map(|e| {
+                Some(TmdbEpisode {
+                    number: e.get("episode_number")?.as_u64()? as u32,
+                    air_date: e.get("air_date").and_then(parse_date),
+                    name: e
+                        .get("name")
+                        .and_then(|n| n.as_str())
+                        .map(Self::sanitise_title),
+                })
+            })
+            .collect();
+
+        Some(episodes)
+    }
+
+    // Find an episode of the given season matching a predicate; each season is only fetched once
+    fn find_episode<T>(
+        &self,
+        id: u64,
+        season: u32,
+        f: impl Fn(&TmdbEpisode) -> Option<T>,
+    ) -> Option<T> {
+        let mut cache = self.episodes.borrow_mut();
+        let episodes = cache
+            .entry((id, season))
+            .or_insert_with(|| self.fetch_episodes(id, season));
+
+        episodes.as_ref()?.iter().find_map(f)
+    }
+
+    fn show_id(&self, show: &str) -> Option<u64> {
+        let mut cache = self.shows.borrow_mut();
+        cache
+            .entry(show.to_string())
+            .or_insert_with(|| self.fetch_show(show))
+            .as_ref()
+            .map(|details| details.id)
+    }
+
+    fn get_first_match(&self, name: &str) -> Option<String> {
+        self.search(name)?.into_iter().next()
+    }
+
+    fn search_movie(&self, title: &str, year: Option<u32>) -> Option<(String, Option<u32>)> {
+        let mut req = self.get("search/movie").query("query", title);
+
+        if let Some(y) = year {
+            req = req.query("year", &y.to_string());
+        }
+
+        let res = req.call().ok()?;
+        let json = res.into_json::<JsValue>().ok()?;
+        let first = json.get("results")?.as_array()?.first()?;
+
+        let found_title = first.get("title")?.as_str().map(Self::sanitise_name)?;
+        let found_year = first
+            .get("release_date")
+            .and_then(|d| d.as_str())
+            .and_then(|d| d.get(0..4))
+            .and_then(|y| y.parse::<u32>().ok());
+
+        Some((found_title, found_year))
+    }
+}
+
+impl MovieResolver for TmdbResolver {
+    // Certainty reflects how closely TMDB's title matches the one we searched for
+    fn resolve_movie(&self, title: &str, year: Option<u32>) -> Option<(String, Option<u32>, f64)> {
+        let (found_title, found_year) = self.search_movie(title, year)?;
+        let certainty = strsim::jaro(&title.to_lowercase(), &found_title.to_lowercase());
+
+        Some((found_title, found_year.or(year), certainty))
+    }
+}
+
+impl EpisodeMapper for TmdbResolver {
+    fn map_absolute(&self, show: &str, absolute: u32) -> Option<(u32, u32)> {
+        let mut cache = self.shows.borrow_mut();
+        let details = cache
+            .entry(show.to_string())
+            .or_insert_with(|| self.fetch_show(show))
+            .as_ref()?;
+
+        let counts: Vec<(u32, u32)> = details
+            .seasons
+            .iter()
+            .map(|s| (s.number, s.episode_count))
+            .collect();
+
+        absolute_to_seasonal(&counts, absolute)
+    }
+
+    // Only seasons which had started by the date can contain the episode; check the most recent
+    // first, as that's almost always the right one
+    fn map_air_date(&self, show: &str, date: NaiveDate) -> Option<(u32, u32)> {
+        let (id, seasons): (u64, Vec<u32>) = {
+            let mut cache = self.shows.borrow_mut();
+            let details = cache
+                .entry(show.to_string())
+                .or_insert_with(|| self.fetch_show(show))
+                .as_ref()?;
+
+            let seasons = details
+                .seasons
+                .iter()
+                .rev()
+                .filter(|s| s.air_date.is_some_and(|d| d <= date))
+                .map(|s| s.number)
+                .collect();
+
+            (details.id, seasons)
+        };
+
+        seasons.into_iter().find_map(|season| {
+            self.find_episode(id, season, |e| {
+                (e.air_date == Some(date)).then_some((season, e.number))
+            })
+        })
+    }
+
+    fn episode_title(&self, show: &str, season: u32, episode: u32) -> Option<String> {
+        let id = self.show_id(show)?;
+        self.find_episode(id, season, |e| {
+            (e.number == episode).then(|| e.name.clone()).flatten()
+        })
     }
 }
 
@@ -58,4 +289,13 @@ impl ShowResolver for TmdbResolver {
     fn resolve(&self, name: &str) -> Option<(String, f64)> {
         self.get_first_match(name).map(|n| (n, 0.1))
     }
+
+    fn candidates(&self, name: &str, limit: usize) -> Vec<(String, f64)> {
+        self.search(name)
+            .unwrap_or_default()
+            .into_iter()
+            .take(limit)
+            .map(|n| (n, 0.1))
+            .collect()
+    }
 }